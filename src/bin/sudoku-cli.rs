@@ -0,0 +1,106 @@
+use druid_play::sudoku::formats::{self, FormatError};
+use druid_play::sudoku::geometry::{Board, Layout};
+use druid_play::sudoku::solver::{self, SolveResult};
+use druid_play::sudoku::techniques;
+
+use std::io::{self, Read, Write};
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: sudoku-cli [FILE]...
+
+Solves every puzzle in the given files, or standard input when there are none or the
+name is '-'. Puzzles are read in any format the sudoku app loads, such as one 81-digit
+line per puzzle. Prints one tab-separated line per puzzle:
+
+    source  number  puzzle  solution  result  grade  milliseconds
+
+where result is unique, multiple or none. A puzzle that can't be read gets a row with
+the result error, and the reason on standard error; the rest of the file is still solved.
+Exits with status 1 if a file or any puzzle in it can't be read.";
+
+fn main() {
+    let mut sources: Vec<String> = std::env::args().skip(1).collect();
+    if sources.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    if sources.is_empty() {
+        sources.push(String::from("-"));
+    }
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut failed = false;
+    for source in sources.iter() {
+        let text = if source == "-" {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map(|_| text)
+        } else {
+            std::fs::read_to_string(source)
+        };
+        let puzzles = match text {
+            Ok(text) => formats::parse_each(&text),
+            Err(e) => {
+                eprintln!("{}: {}", source, e);
+                failed = true;
+                continue;
+            }
+        };
+        if puzzles.is_empty() {
+            eprintln!("{}: {}", source, FormatError::NoPuzzle);
+            failed = true;
+        }
+        for (number, puzzle) in puzzles.iter().enumerate() {
+            let written = match puzzle {
+                Ok(puzzle) => report(&mut out, source, number + 1, puzzle),
+                Err(e) => {
+                    eprintln!("{}: puzzle {}: {}", source, number + 1, e);
+                    failed = true;
+                    writeln!(out, "{}\t{}\t-\t-\terror\t-\t-", source, number + 1)
+                }
+            };
+            // A closed pipe, as with `| head`, just means nobody wants the rest.
+            if written.is_err() {
+                return;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn report(out: &mut impl Write, source: &str, number: usize, puzzle: &Board) -> io::Result<()> {
+    let layout = Layout::new(puzzle.geometry, &puzzle.variant);
+    let mut board = puzzle.values.clone();
+    let start = Instant::now();
+    let result = solver::solve(&layout, &mut board);
+    let elapsed = start.elapsed();
+    let (result, solution, grade) = match result {
+        SolveResult::Unique => (
+            "unique",
+            line(puzzle, &board),
+            techniques::grade(&layout, &puzzle.values).name(),
+        ),
+        SolveResult::Multiple => ("multiple", line(puzzle, &board), "-"),
+        SolveResult::None => ("none", String::from("-"), "-"),
+    };
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{:.3}",
+        source,
+        number,
+        line(puzzle, &puzzle.values),
+        solution,
+        result,
+        grade,
+        elapsed.as_secs_f64() * 1000.
+    )
+}
+
+fn line(puzzle: &Board, values: &[u8]) -> String {
+    formats::to_line(&Board {
+        values: values.to_vec(),
+        ..puzzle.clone()
+    })
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::stats::{self, home_path};
+use druid_play::sudoku::generator::{self, Puzzle};
+use druid_play::sudoku::geometry::Geometry;
+use druid_play::sudoku::rules::Variant;
+use druid_play::sudoku::techniques::Difficulty;
+
+const DAILY_FILE: &str = ".sudoku_daily.json";
+const DAILY_DIFFICULTY: Difficulty = Difficulty::Medium;
+
+// Days since 1970-01-01 in UTC, so everyone gets the next puzzle at the same moment.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() / 86400)
+        .unwrap_or(0)
+}
+
+// The day as YYYY-MM-DD, converted from the days since the epoch without needing a date library.
+pub fn date(day: u64) -> String {
+    let days = day as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02}", year, month, day_of_month)
+}
+
+// Always a classic board with no extra rules, whatever is being played at the moment, so there's
+// always a grid to be had.
+pub fn puzzle(day: u64) -> Puzzle {
+    generator::generate_seeded(
+        Geometry::CLASSIC,
+        &Variant::default(),
+        DAILY_DIFFICULTY,
+        day,
+    )
+    .expect("a classic grid always exists")
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub seconds: f64,
+    pub mistakes: u32,
+    // The emoji grid for the summary, one line per row.
+    pub pattern: String,
+}
+
+// Each day's first completion, keyed by day number and kept in the home directory.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub days: BTreeMap<u64, DailyResult>,
+}
+
+impl History {
+    // A missing or unreadable file just means no daily puzzles solved yet.
+    pub fn load() -> History {
+        std::fs::read_to_string(home_path(DAILY_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(
+            home_path(DAILY_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+
+    // Replaying a day doesn't replace its result. Returns whether this was the first.
+    pub fn record(&mut self, day: u64, result: DailyResult) -> bool {
+        if self.days.contains_key(&day) {
+            return false;
+        }
+        self.days.insert(day, result);
+        true
+    }
+
+    // Consecutive days up to today, or up to yesterday while today's puzzle is still open.
+    pub fn current_streak(&self, today: u64) -> usize {
+        let last = if self.days.contains_key(&today) {
+            today
+        } else {
+            today.saturating_sub(1)
+        };
+        (0..=last)
+            .rev()
+            .take_while(|day| self.days.contains_key(day))
+            .count()
+    }
+
+    pub fn longest_streak(&self) -> usize {
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut previous = Option::None;
+        for &day in self.days.keys() {
+            streak = if previous == Some(day.wrapping_sub(1)) {
+                streak + 1
+            } else {
+                1
+            };
+            longest = longest.max(streak);
+            previous = Some(day);
+        }
+        longest
+    }
+
+    // Something to paste into a chat, giving away the time and how it went but no digits.
+    pub fn summary(&self, day: u64) -> Option<String> {
+        let result = self.days.get(&day)?;
+        Some(format!(
+            "Sudoku Daily {}\n⏱️ {} · ❌ {} · 🔥 {}\n\n{}",
+            date(day),
+            stats::format_time(result.seconds),
+            result.mistakes,
+            self.current_streak(day),
+            result.pattern
+        ))
+    }
+}
+
+// Givens are white, digits that went in right first time green, and cells that ever held a
+// wrong digit yellow.
+pub fn pattern(size: usize, given: &[bool], missed: &[bool]) -> String {
+    given
+        .chunks(size)
+        .zip(missed.chunks(size))
+        .map(|(given, missed)| {
+            given
+                .iter()
+                .zip(missed.iter())
+                .map(|(&given, &missed)| {
+                    if given {
+                        '⬜'
+                    } else if missed {
+                        '🟨'
+                    } else {
+                        '🟩'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
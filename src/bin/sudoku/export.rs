@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use druid::kurbo::Line;
+use druid::piet::{
+    Device, FontFamily, FontWeight, ImageFormat, RenderContext, StrokeStyle, Text, TextLayout,
+    TextLayoutBuilder,
+};
+use druid::Color;
+use druid_play::sudoku::print::{Ink, Mark, Page};
+
+// Pixels per point, so a board page comes out at 960 pixels square.
+const PNG_SCALE: f64 = 2.;
+
+fn color(ink: Ink) -> Color {
+    let (r, g, b) = ink.rgb();
+    Color::rgb8(r, g, b)
+}
+
+// Draws the page with piet, the same way the board is painted on screen, and saves it
+// through the image crate.
+pub fn to_png(page: &Page, path: &Path) -> Result<(), String> {
+    let width = (page.width * PNG_SCALE) as usize;
+    let height = (page.height * PNG_SCALE) as usize;
+    let mut device = Device::new().map_err(|e| e.to_string())?;
+    let mut target = device
+        .bitmap_target(width, height, PNG_SCALE)
+        .map_err(|e| e.to_string())?;
+    {
+        let mut ctx = target.render_context();
+        ctx.clear(Color::WHITE);
+        let dashes = StrokeStyle::new().dash(vec![3.0, 3.0], 0.0);
+        for mark in page.marks.iter() {
+            match mark {
+                Mark::Line {
+                    from,
+                    to,
+                    width,
+                    dashed,
+                    ink,
+                } => {
+                    let line = Line::new(*from, *to);
+                    if *dashed {
+                        ctx.stroke_styled(line, &color(*ink), *width, &dashes);
+                    } else {
+                        ctx.stroke(line, &color(*ink), *width);
+                    }
+                }
+                Mark::Text {
+                    at,
+                    size,
+                    text,
+                    ink,
+                } => {
+                    let layout = ctx
+                        .text()
+                        .new_text_layout(text.clone())
+                        .font(FontFamily::SANS_SERIF, *size)
+                        .default_attribute(if ink.is_bold() {
+                            FontWeight::BOLD
+                        } else {
+                            FontWeight::NORMAL
+                        })
+                        .text_color(color(*ink))
+                        .build()
+                        .map_err(|e| e.to_string())?;
+                    let bounds = layout.size();
+                    let x = if *ink == Ink::Caption {
+                        at.0
+                    } else {
+                        at.0 - bounds.width / 2.
+                    };
+                    ctx.draw_text(&layout, (x, at.1 - bounds.height / 2.));
+                }
+            }
+        }
+        ctx.finish().map_err(|e| e.to_string())?;
+    }
+    let mut pixels = vec![0; width * height * 4];
+    target
+        .copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels)
+        .map_err(|e| e.to_string())?;
+    // Everything is drawn over an opaque white background, so premultiplying changed nothing.
+    image::RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .ok_or_else(|| String::from("Bitmap has the wrong size"))?
+        .save(path)
+        .map_err(|e| e.to_string())
+}
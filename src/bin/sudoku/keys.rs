@@ -0,0 +1,174 @@
+use druid::{Code, Data, KbKey, KeyEvent};
+use serde::{Deserialize, Serialize};
+
+use druid_play::sudoku::geometry::Geometry;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// What the arrow keys do at the edge of the board: stay put, come back in on the other side of
+// the same row or column, or carry on into the next row or column.
+#[derive(Clone, Copy, Data, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Edge {
+    #[default]
+    Stop,
+    Wrap,
+    Continue,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    // 0 clears the cell.
+    Enter(u8),
+    ToggleNotes,
+    Move(Direction),
+    // To the same place in the neighbouring box.
+    JumpBox(Direction),
+    RowStart,
+    RowEnd,
+    BoardStart,
+    BoardEnd,
+    NextEmpty,
+    PreviousEmpty,
+}
+
+// Every key the board responds to. Digits go by the board's symbols, and '.', '0' or space
+// clear a cell as they mark a blank in puzzle files.
+pub fn action(key: &KeyEvent, geometry: Geometry) -> Option<Action> {
+    let ctrl = key.mods.ctrl();
+    let shift = key.mods.shift();
+    let action = match &key.key {
+        KbKey::Character(text) => {
+            let mut chars = text.chars();
+            let c = match (numpad_digit(key.code), chars.next(), chars.next()) {
+                (Some(digit), _, _) => digit,
+                (Option::None, Some(c), Option::None) => c,
+                _ => return Option::None,
+            };
+            match geometry.value_of(c) {
+                Some(value) => Action::Enter(value),
+                Option::None if c == ' ' || geometry.is_blank(c) => Action::Enter(0),
+                Option::None if key.code == Code::KeyN => Action::ToggleNotes,
+                Option::None => return Option::None,
+            }
+        }
+        KbKey::Backspace | KbKey::Delete => Action::Enter(0),
+        KbKey::ArrowUp if ctrl => Action::JumpBox(Direction::Up),
+        KbKey::ArrowDown if ctrl => Action::JumpBox(Direction::Down),
+        KbKey::ArrowLeft if ctrl => Action::JumpBox(Direction::Left),
+        KbKey::ArrowRight if ctrl => Action::JumpBox(Direction::Right),
+        KbKey::ArrowUp => Action::Move(Direction::Up),
+        KbKey::ArrowDown => Action::Move(Direction::Down),
+        KbKey::ArrowLeft => Action::Move(Direction::Left),
+        KbKey::ArrowRight => Action::Move(Direction::Right),
+        KbKey::Home if ctrl => Action::BoardStart,
+        KbKey::End if ctrl => Action::BoardEnd,
+        KbKey::Home => Action::RowStart,
+        KbKey::End => Action::RowEnd,
+        KbKey::Tab if shift => Action::PreviousEmpty,
+        KbKey::Tab => Action::NextEmpty,
+        _ => return Option::None,
+    };
+    Some(action)
+}
+
+// Numpad digits go by the key, whatever character the keyboard layout gives them. With Num Lock
+// off they arrive as the arrows, Home and End printed on them instead.
+fn numpad_digit(code: Code) -> Option<char> {
+    let digit = match code {
+        Code::Numpad0 => '0',
+        Code::Numpad1 => '1',
+        Code::Numpad2 => '2',
+        Code::Numpad3 => '3',
+        Code::Numpad4 => '4',
+        Code::Numpad5 => '5',
+        Code::Numpad6 => '6',
+        Code::Numpad7 => '7',
+        Code::Numpad8 => '8',
+        Code::Numpad9 => '9',
+        _ => return Option::None,
+    };
+    Some(digit)
+}
+
+// The cell a movement action goes to from `index`, or None if it stays where it is.
+pub fn target(
+    action: Action,
+    geometry: Geometry,
+    index: usize,
+    values: &[u8],
+    edge: Edge,
+) -> Option<usize> {
+    let size = geometry.size();
+    let row = geometry.row_of(index);
+    let target = match action {
+        Action::Enter(_) | Action::ToggleNotes => return Option::None,
+        Action::Move(direction) => step(geometry, index, direction, 1, edge),
+        Action::JumpBox(direction) => {
+            let distance = match direction {
+                Direction::Up | Direction::Down => geometry.box_rows,
+                Direction::Left | Direction::Right => geometry.box_cols,
+            };
+            step(geometry, index, direction, distance, edge)
+        }
+        Action::RowStart => row * size,
+        Action::RowEnd => row * size + size - 1,
+        Action::BoardStart => 0,
+        Action::BoardEnd => geometry.cells() - 1,
+        Action::NextEmpty => (1..values.len())
+            .map(|offset| (index + offset) % values.len())
+            .find(|&other| values[other] == 0)?,
+        Action::PreviousEmpty => (1..values.len())
+            .map(|offset| (index + values.len() - offset) % values.len())
+            .find(|&other| values[other] == 0)?,
+    };
+    if target == index {
+        Option::None
+    } else {
+        Some(target)
+    }
+}
+
+// Moves `distance` cells along a row or column. Continuing past the edge follows the rows in
+// reading order for left and right, and the columns top to bottom for up and down.
+fn step(
+    geometry: Geometry,
+    index: usize,
+    direction: Direction,
+    distance: usize,
+    edge: Edge,
+) -> usize {
+    let size = geometry.size() as isize;
+    let (row, col) = (
+        geometry.row_of(index) as isize,
+        geometry.col_of(index) as isize,
+    );
+    let distance = distance as isize;
+    let (vertical, delta) = match direction {
+        Direction::Up => (true, -distance),
+        Direction::Down => (true, distance),
+        Direction::Left => (false, -distance),
+        Direction::Right => (false, distance),
+    };
+    // Position along the line being moved on, and which line that is.
+    let (along, line) = if vertical { (row, col) } else { (col, row) };
+    let (along, line) = match edge {
+        Edge::Stop => ((along + delta).max(0).min(size - 1), line),
+        Edge::Wrap => ((along + delta).rem_euclid(size), line),
+        Edge::Continue => {
+            let position = (line * size + along + delta).rem_euclid(size * size);
+            (position % size, position / size)
+        }
+    };
+    let (row, col) = if vertical {
+        (along, line)
+    } else {
+        (line, along)
+    };
+    (row * size + col) as usize
+}
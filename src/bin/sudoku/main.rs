@@ -0,0 +1,2376 @@
+use druid::kurbo::Line;
+use druid::piet::{
+    FontFamily, FontStyle, FontWeight, StrokeStyle, Text, TextLayout, TextLayoutBuilder,
+};
+use druid::widget::prelude::*;
+use druid::widget::{
+    Button, Checkbox, CrossAxisAlignment, Flex, IdentityWrapper, Label, LineBreaking, RadioGroup,
+    Scroll, ViewSwitcher,
+};
+use druid::widget::{Controller, Either};
+use druid::AppDelegate;
+use druid::Application;
+use druid::Command;
+use druid::DelegateCtx;
+use druid::ExtEventSink;
+use druid::FileDialogOptions;
+use druid::FileSpec;
+use druid::Handled;
+use druid::MenuDesc;
+use druid::MenuItem;
+use druid::SysMods;
+use druid::Target;
+use druid::{
+    AppLauncher, BoxConstraints, Color, Data, Env, Event, EventCtx, LayoutCtx, Lens, LensExt,
+    LifeCycle, LifeCycleCtx, LocalizedString, PaintCtx, Point, Rect, Selector, Size, TimerToken,
+    UpdateCtx, Widget, WidgetExt, WidgetPod, WindowDesc,
+};
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod daily;
+mod export;
+mod keys;
+mod session;
+mod settings;
+mod stats;
+
+use daily::{DailyResult, History};
+use druid_play::sudoku::conflicts;
+use druid_play::sudoku::formats;
+use druid_play::sudoku::generator::{self, Puzzle};
+use druid_play::sudoku::geometry::{Board, Geometry, Layout, SIZES};
+use druid_play::sudoku::print;
+use druid_play::sudoku::recognize;
+use druid_play::sudoku::rules::{Toggle, Variant};
+use druid_play::sudoku::solver::{self, Observer, SolveResult};
+use druid_play::sudoku::techniques::{self, Deduction, Difficulty};
+use keys::{Action, Edge};
+use serde::{Deserialize, Serialize};
+use session::Session;
+use settings::Settings;
+use stats::Stats;
+
+const SOLVE: Selector = Selector::new("SOLVE");
+const REQUEST_FOCUS: Selector = Selector::new("REQUEST_FOCUS");
+const REPAINT: Selector = Selector::new("REPAINT");
+// Each message from a solver thread carries that solve's cancel flag, so anything still arriving
+// from a cancelled solve can be told apart from the current one.
+const SOLVE_COMPLETE: Selector<(Arc<AtomicBool>, SolveResult, Vec<u8>)> =
+    Selector::new("SOLVE_COMPLETE");
+const CANCEL_SOLVE: Selector = Selector::new("CANCEL_SOLVE");
+// Nodes explored and the current guess depth of a running solve.
+const SOLVE_PROGRESS: Selector<(Arc<AtomicBool>, u64, usize)> = Selector::new("SOLVE_PROGRESS");
+const WATCH_SOLVE: Selector = Selector::new("WATCH_SOLVE");
+// The board the animated search is looking at, and the cells it has guessed so far.
+const ANIMATION_STEP: Selector<(Arc<AtomicBool>, Vec<u8>, Vec<usize>)> =
+    Selector::new("ANIMATION_STEP");
+const ANIMATION_FASTER: Selector = Selector::new("ANIMATION_FASTER");
+const ANIMATION_SLOWER: Selector = Selector::new("ANIMATION_SLOWER");
+const USER_COLOR: Color = Color::rgb8(0, 64, 160);
+const SOLVER_COLOR: Color = Color::rgb8(0, 128, 64);
+const HINT_COLOR: Color = Color::rgb8(255, 240, 160);
+const HINT_TARGET_COLOR: Color = Color::rgb8(255, 190, 110);
+const RULE_COLOR: Color = Color::rgb8(120, 120, 120);
+const RELATED_COLOR: Color = Color::rgb8(232, 240, 248);
+const SAME_DIGIT_COLOR: Color = Color::rgb8(190, 215, 250);
+const SELECTION_COLOR: Color = Color::rgb8(200, 235, 245);
+const GUESS_COLOR: Color = Color::rgb8(255, 210, 210);
+const TENTATIVE_COLOR: Color = Color::rgb8(150, 90, 170);
+const BANNER_COLOR: Color = Color::rgb8(200, 240, 200);
+
+const TOGGLE_NOTES: Selector = Selector::new("TOGGLE_NOTES");
+const HINT: Selector = Selector::new("HINT");
+const NEW_PUZZLE: Selector<Difficulty> = Selector::new("NEW_PUZZLE");
+// Generated puzzles carry the cancel flag of the generation, the same way solves do.
+// None when no grid exists for the rules in play.
+const PUZZLE_GENERATED: Selector<(Arc<AtomicBool>, Option<Puzzle>)> =
+    Selector::new("PUZZLE_GENERATED");
+const DAILY_PUZZLE: Selector = Selector::new("DAILY_PUZZLE");
+const DAILY_GENERATED: Selector<(Arc<AtomicBool>, u64, Puzzle)> = Selector::new("DAILY_GENERATED");
+const COPY_DAILY_SUMMARY: Selector = Selector::new("COPY_DAILY_SUMMARY");
+const EXPLAIN_CONFLICTS: Selector = Selector::new("EXPLAIN_CONFLICTS");
+const ENTRIES_TO_REMOVE: Selector<(Vec<u8>, Option<Vec<usize>>)> =
+    Selector::new("ENTRIES_TO_REMOVE");
+const LOAD_PUZZLE: Selector<(usize, Board)> = Selector::new("LOAD_PUZZLE");
+const SET_GEOMETRY: Selector<Geometry> = Selector::new("SET_GEOMETRY");
+const TOGGLE_RULE: Selector<Toggle> = Selector::new("TOGGLE_RULE");
+const TOGGLE_PAUSE: Selector = Selector::new("TOGGLE_PAUSE");
+const SHOW_STATS: Selector = Selector::new("SHOW_STATS");
+const SHOW_SETTINGS: Selector = Selector::new("SHOW_SETTINGS");
+const CONFIRM_IMPORT: Selector = Selector::new("CONFIRM_IMPORT");
+const TOGGLE_CHECK: Selector = Selector::new("TOGGLE_CHECK");
+const REVEAL_CELL: Selector = Selector::new("REVEAL_CELL");
+// The puzzle that was solved in the background, and its solution if it has exactly one.
+const SOLUTION_FOUND: Selector<(Vec<u8>, Option<Vec<u8>>)> = Selector::new("SOLUTION_FOUND");
+// And its grade, which is only worth having with a unique solution.
+const PUZZLE_GRADED: Selector<(Vec<u8>, Difficulty)> = Selector::new("PUZZLE_GRADED");
+const EXPORT_BOOKLET: Selector = Selector::new("EXPORT_BOOKLET");
+const BOOKLET_EXPORTED: Selector<(Arc<AtomicBool>, Result<usize, String>)> =
+    Selector::new("BOOKLET_EXPORTED");
+
+// The grid's side when nothing limits it; otherwise it fills the space it's given.
+const GRID_SIZE: f64 = 432.;
+const CLOCK_TICK: Duration = Duration::from_millis(250);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const ANIMATION_DELAYS: std::ops::RangeInclusive<u64> = 1..=2000;
+const BOOKLET_PUZZLES: usize = 12;
+const NO_GRID: &str = "No grid exists for this rule combination";
+
+struct Square {
+    grid_index: usize,
+}
+
+// Lays the squares out to fill the space it's given, and draws every grid line over them in one
+// pass: thin between cells, thick between boxes or jigsaw regions and around the outside.
+struct Grid {
+    size: usize,
+    squares: Vec<WidgetPod<AppState, Box<dyn Widget<AppState>>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct CellState {
+    value: u8,
+    notes: u16,
+    from_solver: bool,
+    // Candidates hints have ruled out, so undoing a hint brings its deduction back.
+    eliminated: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CellEdit {
+    index: usize,
+    before: CellState,
+    after: CellState,
+}
+
+#[derive(Clone, Lens, Data)]
+struct AppState {
+    layout: Arc<Layout>,
+    widget_ids: Arc<Vec<WidgetId>>,
+    values: Arc<Vec<u8>>,
+    is_legal: Arc<Vec<bool>>,
+    given: Arc<Vec<bool>>,
+    from_solver: Arc<Vec<bool>>,
+    notes: Arc<Vec<u16>>,
+    notes_mode: bool,
+    eliminated: Arc<Vec<u16>>,
+    hint_cells: Arc<Vec<usize>>,
+    hint_targets: Arc<Vec<usize>>,
+    undo_stack: Arc<Vec<Vec<CellEdit>>>,
+    redo_stack: Arc<Vec<Vec<CellEdit>>>,
+    solving: bool,
+    status: String,
+    // Seconds spent on the current puzzle, and its grade when it came from the generator.
+    elapsed: f64,
+    paused: bool,
+    finished: bool,
+    #[data(same_fn = "PartialEq::eq")]
+    difficulty: Option<Difficulty>,
+    // The answer, when known, and how many wrong digits have been entered against it.
+    solution: Option<Arc<Vec<u8>>>,
+    mistakes: u32,
+    check_mode: bool,
+    // The day of the daily puzzle being played, and cells that have ever held a wrong digit.
+    daily: Option<u64>,
+    missed: Arc<Vec<bool>>,
+    // Set while the solver thread runs; raising the flag asks it to stop.
+    cancel_solve: Option<Arc<AtomicBool>>,
+    // What an animated solve is showing; the delay between its steps can change while it runs.
+    animation: Option<Arc<Vec<u8>>>,
+    guesses: Arc<Vec<usize>>,
+    animation_delay: Arc<AtomicU64>,
+    // Cells that digits and notes go into; usually just the focused one.
+    selection: Arc<Vec<usize>>,
+    focus: Option<usize>,
+    settings: Settings,
+    // Digits read from an image sit on the board as ordinary entries until they're confirmed.
+    importing: bool,
+}
+
+impl AppState {
+    fn new(geometry: Geometry) -> AppState {
+        let mut state = AppState {
+            layout: Arc::new(Layout::new(geometry, &Variant::default())),
+            widget_ids: Arc::new(Vec::new()),
+            values: Arc::new(Vec::new()),
+            is_legal: Arc::new(Vec::new()),
+            given: Arc::new(Vec::new()),
+            from_solver: Arc::new(Vec::new()),
+            notes: Arc::new(Vec::new()),
+            notes_mode: false,
+            eliminated: Arc::new(Vec::new()),
+            hint_cells: Arc::new(Vec::new()),
+            hint_targets: Arc::new(Vec::new()),
+            undo_stack: Arc::new(Vec::new()),
+            redo_stack: Arc::new(Vec::new()),
+            solving: false,
+            status: String::new(),
+            elapsed: 0.,
+            paused: false,
+            finished: false,
+            difficulty: Option::None,
+            solution: Option::None,
+            mistakes: 0,
+            check_mode: false,
+            daily: Option::None,
+            missed: Arc::new(Vec::new()),
+            cancel_solve: Option::None,
+            animation: Option::None,
+            guesses: Arc::new(Vec::new()),
+            animation_delay: Arc::new(AtomicU64::new(100)),
+            selection: Arc::new(Vec::new()),
+            focus: Option::None,
+            settings: Settings::load(),
+            importing: false,
+        };
+        state.load_board(&Board::empty(geometry));
+        state
+    }
+
+    fn geometry(&self) -> Geometry {
+        self.layout.geometry
+    }
+
+    // What the grid of squares is built from: the box shape.
+    fn grid_shape(&self) -> (usize, usize) {
+        let geometry = self.geometry();
+        (geometry.box_rows, geometry.box_cols)
+    }
+
+    // A different grid shape means new squares, so the widget ids are replaced as well.
+    fn load_board(&mut self, board: &Board) {
+        self.cancel();
+        let cells = board.geometry.cells();
+        let shape = (board.geometry.box_rows, board.geometry.box_cols);
+        if shape != self.grid_shape() || self.widget_ids.len() != cells {
+            self.widget_ids = Arc::new((0..cells).map(|_| WidgetId::next()).collect());
+        }
+        self.layout = Arc::new(Layout::new(board.geometry, &board.variant));
+        self.values = Arc::new(board.values.clone());
+        self.is_legal = Arc::new(vec![true; cells]);
+        self.refresh_legality();
+        self.given = Arc::new(board.values.iter().map(|&value| value != 0).collect());
+        self.from_solver = Arc::new(vec![false; cells]);
+        self.notes = Arc::new(vec![0; cells]);
+        self.eliminated = Arc::new(vec![0; cells]);
+        self.clear_hint();
+        self.undo_stack = Arc::new(Vec::new());
+        self.redo_stack = Arc::new(Vec::new());
+        self.elapsed = 0.;
+        self.paused = false;
+        self.finished = false;
+        self.difficulty = Option::None;
+        self.solution = Option::None;
+        self.mistakes = 0;
+        self.daily = Option::None;
+        self.missed = Arc::new(vec![false; cells]);
+        self.selection = Arc::new(Vec::new());
+        self.focus = Option::None;
+        self.importing = false;
+    }
+
+    fn import(&mut self, board: &Board) {
+        self.load_board(&Board {
+            values: vec![0; board.values.len()],
+            ..board.clone()
+        });
+        self.values = Arc::new(board.values.clone());
+        self.refresh_legality();
+        self.importing = true;
+        self.status = format!(
+            "Read {} digits from the image. Correct any misread cells, then choose Confirm Import",
+            board.values.iter().filter(|&&value| value != 0).count()
+        );
+    }
+
+    // Shares a row, column or box (or jigsaw region) with the focused cell.
+    fn is_related(&self, index: usize) -> bool {
+        let geometry = self.geometry();
+        match self.focus {
+            Some(focus) => {
+                geometry.row_of(index) == geometry.row_of(focus)
+                    || geometry.col_of(index) == geometry.col_of(focus)
+                    || self.layout.regions[index] == self.layout.regions[focus]
+            }
+            Option::None => false,
+        }
+    }
+
+    fn focused_digit(&self) -> Option<u8> {
+        self.focus
+            .map(|focus| self.values[focus])
+            .filter(|&value| value != 0)
+    }
+
+    // A plain click selects just the cell; shift or ctrl adds it to, or takes it out of, the
+    // selection.
+    fn select(&mut self, index: usize, extend: bool) {
+        self.focus = Some(index);
+        let selection = Arc::make_mut(&mut self.selection);
+        if !extend {
+            selection.clear();
+            selection.push(index);
+        } else if let Some(position) = selection.iter().position(|&i| i == index) {
+            selection.remove(position);
+        } else {
+            selection.push(index);
+        }
+    }
+
+    // Puts the digit, or its note, into every selected cell the player may change, as a single
+    // undoable step. A note is added to all of them unless they all have it already.
+    fn enter(&mut self, value: u8) -> Vec<usize> {
+        if self.solving || self.paused || self.solved_by_hand() {
+            return Vec::new();
+        }
+        let cells: Vec<usize> = self
+            .selection
+            .iter()
+            .copied()
+            .filter(|&index| !self.given[index])
+            .collect();
+        let before = self.snapshot();
+        let mut modified = Vec::new();
+        if self.notes_mode && value != 0 {
+            let bit = 1 << (value - 1);
+            let add = cells
+                .iter()
+                .any(|&index| self.values[index] == 0 && self.notes[index] & bit == 0);
+            for &index in cells.iter() {
+                if self.values[index] == 0 && (self.notes[index] & bit != 0) != add {
+                    modified.append(&mut self.toggle_note(index, value));
+                }
+            }
+        } else {
+            for &index in cells.iter() {
+                if self.notes_mode {
+                    modified.append(&mut self.toggle_note(index, value));
+                } else {
+                    modified.append(&mut self.set_value(index, value));
+                    if self.is_mistake(index) {
+                        self.mistakes += 1;
+                        Arc::make_mut(&mut self.missed)[index] = true;
+                    }
+                }
+            }
+        }
+        self.record_edit(before);
+        self.clear_hint();
+        self.update_progress();
+        modified
+    }
+
+    // The clock runs while a puzzle with givens is being worked on.
+    fn is_clock_running(&self) -> bool {
+        !self.paused && !self.finished && !self.solving && self.given.contains(&true)
+    }
+
+    fn is_full(&self) -> bool {
+        !self.values.contains(&0) && self.is_legal.iter().all(|&x| x)
+    }
+
+    // A full board only counts as solved if it also matches the known solution.
+    fn is_solved(&self) -> bool {
+        self.is_full()
+            && match &self.solution {
+                Some(solution) => solution.as_slice() == self.values.as_slice(),
+                Option::None => true,
+            }
+    }
+
+    // Only a completion the player made themselves freezes the board; one the solver finished
+    // can still be edited or undone.
+    fn solved_by_hand(&self) -> bool {
+        self.finished && !self.from_solver.contains(&true)
+    }
+
+    // A digit is a mistake if it differs from the solution, or, without one, if it clashes
+    // with the board.
+    fn is_mistake(&self, index: usize) -> bool {
+        let value = self.values[index];
+        value != 0
+            && match &self.solution {
+                Some(solution) => solution[index] != value,
+                Option::None => !self.is_legal[index],
+            }
+    }
+
+    // Only meaningful in check mode, once the solution is known.
+    fn is_wrong(&self, index: usize) -> bool {
+        self.check_mode && self.solution.is_some() && !self.given[index] && self.is_mistake(index)
+    }
+
+    fn puzzle(&self) -> Vec<u8> {
+        self.values
+            .iter()
+            .zip(self.given.iter())
+            .map(|(&value, &given)| if given { value } else { 0 })
+            .collect()
+    }
+
+    // Solves and grades the givens on another thread, so entries can be checked and a puzzle
+    // that didn't come from the generator still counts towards the best times.
+    fn find_solution(&self, sink: ExtEventSink) {
+        if self.solution.is_some() || !self.given.contains(&true) {
+            return;
+        }
+        let puzzle = self.puzzle();
+        let layout = self.layout.clone();
+        std::thread::spawn(move || {
+            let mut board = puzzle.clone();
+            let solution = match solver::solve(&layout, &mut board) {
+                SolveResult::Unique => Some(board),
+                _ => Option::None,
+            };
+            let unique = solution.is_some();
+            sink.submit_command(SOLUTION_FOUND, (puzzle.clone(), solution), Target::Global)
+                .unwrap();
+            if unique {
+                let grade = techniques::grade(&layout, &puzzle);
+                sink.submit_command(PUZZLE_GRADED, (puzzle, grade), Target::Global)
+                    .unwrap()
+            }
+        });
+    }
+
+    // Fills the cell from the solution. Revealed cells count as the computer's, so the
+    // puzzle no longer qualifies for a best time.
+    fn reveal(&mut self, index: usize) -> Vec<usize> {
+        let value = match &self.solution {
+            Some(solution) => solution[index],
+            Option::None => {
+                self.status = String::from("The solution isn't known for this puzzle");
+                return Vec::new();
+            }
+        };
+        if self.given[index] || self.values[index] == value {
+            return Vec::new();
+        }
+        let before = self.snapshot();
+        let modified = self.set_value(index, value);
+        Arc::make_mut(&mut self.from_solver)[index] = true;
+        self.record_edit(before);
+        self.clear_hint();
+        self.update_progress();
+        modified
+    }
+
+    // Notices the board being completed. Only a board the player filled in themselves counts
+    // towards the best times, and freezes it until the next puzzle.
+    fn update_progress(&mut self) {
+        let finished = self.is_solved();
+        if !finished && self.is_full() {
+            self.status = String::from("The board is full, but it doesn't match the solution");
+        }
+        if finished && !self.finished && !self.from_solver.contains(&true) {
+            self.status = format!(
+                "Solved in {} with {} mistake{}",
+                stats::format_time(self.elapsed),
+                self.mistakes,
+                if self.mistakes == 1 { "" } else { "s" }
+            );
+            if let Some(difficulty) = self.difficulty {
+                let mut stats = Stats::load();
+                if stats.record(self.geometry(), difficulty, self.elapsed) {
+                    self.status = format!(
+                        "{}, a new best for {}",
+                        self.status,
+                        stats::key(self.geometry(), difficulty)
+                    );
+                }
+                if let Err(e) = stats.save() {
+                    self.status = format!("{} (couldn't save statistics: {})", self.status, e);
+                }
+            }
+            if let Some(day) = self.daily {
+                self.record_daily(day);
+            }
+        }
+        self.finished = finished;
+    }
+
+    fn record_daily(&mut self, day: u64) {
+        let mut history = History::load();
+        let first = history.record(
+            day,
+            DailyResult {
+                seconds: self.elapsed,
+                mistakes: self.mistakes,
+                pattern: daily::pattern(self.geometry().size(), &self.given, &self.missed),
+            },
+        );
+        if !first {
+            return;
+        }
+        self.status = format!(
+            "{}, daily streak {}",
+            self.status,
+            history.current_streak(day)
+        );
+        if let Err(e) = history.save() {
+            self.status = format!("{} (couldn't save daily history: {})", self.status, e);
+        }
+    }
+
+    // Switching rules keeps the entries but rechecks them; eliminations from earlier hints may
+    // no longer hold, so they're dropped, and the puzzle is solved and graded again.
+    fn set_variant(&mut self, variant: Variant) {
+        let cells = self.geometry().cells();
+        self.layout = Arc::new(Layout::new(self.geometry(), &variant));
+        self.eliminated = Arc::new(vec![0; cells]);
+        self.solution = Option::None;
+        self.difficulty = Option::None;
+        self.clear_hint();
+        self.refresh_legality();
+    }
+
+    fn clear_hint(&mut self) {
+        self.hint_cells = Arc::new(Vec::new());
+        self.hint_targets = Arc::new(Vec::new());
+    }
+
+    // Highlights the next deduction. Eliminations are remembered so the following hint moves on,
+    // and are struck from any notes the player has made.
+    fn hint(&mut self) {
+        if !self.is_legal.iter().all(|&x| x) {
+            self.status = String::from("Fix the conflicting cells before asking for a hint");
+            return;
+        }
+        match techniques::find_hint(&self.layout, &self.values, &self.eliminated) {
+            Some(step) => {
+                if let Deduction::Eliminate(eliminations) = &step.deduction {
+                    let before = self.snapshot();
+                    let eliminated = Arc::make_mut(&mut self.eliminated);
+                    let notes = Arc::make_mut(&mut self.notes);
+                    for &(index, value) in eliminations {
+                        eliminated[index] |= 1 << (value - 1);
+                        notes[index] &= !(1 << (value - 1));
+                    }
+                    self.record_edit(before);
+                }
+                self.status = step.explain(&self.layout);
+                self.hint_cells = Arc::new(step.cells.clone());
+                self.hint_targets = Arc::new(step.targets());
+            }
+            Option::None => {
+                self.clear_hint();
+                self.status = String::from(if self.values.contains(&0) {
+                    "No hint available: this position needs guessing"
+                } else {
+                    "The board is already full"
+                });
+            }
+        }
+    }
+
+    // Names what the illegal cells clash with, keeping to the focused cell when it's one of
+    // them, and returns the explanations. A board with no clashes is checked on another thread
+    // for entries that stop it being solved.
+    fn explain_conflicts(&mut self, sink: ExtEventSink) -> Vec<String> {
+        self.clear_hint();
+        let mut found = conflicts::conflicts(&self.layout, &self.values);
+        if found.is_empty() {
+            self.status = String::from("No conflicts; checking the board can still be solved...");
+            let layout = self.layout.clone();
+            let board = self.values.to_vec();
+            let given = self.given.to_vec();
+            std::thread::spawn(move || {
+                let entries = conflicts::entries_to_remove(&layout, &board, &given);
+                sink.submit_command(ENTRIES_TO_REMOVE, (board, entries), Target::Global)
+                    .unwrap()
+            });
+            return Vec::new();
+        }
+        if let Some(focus) = self.focus {
+            if !self.is_legal[focus] {
+                found.retain(|conflict| conflict.involves(focus));
+            }
+        }
+        let mut cells: Vec<usize> = found.iter().flat_map(|conflict| conflict.cells()).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        self.hint_cells = Arc::new(cells);
+        let explanations: Vec<String> = found
+            .iter()
+            .map(|conflict| conflict.explain(&self.layout))
+            .collect();
+        self.status = if explanations.len() == 1 {
+            explanations[0].clone()
+        } else {
+            format!("{} conflicts", explanations.len())
+        };
+        explanations
+    }
+
+    fn show_entries_to_remove(&mut self, entries: &Option<Vec<usize>>) {
+        self.clear_hint();
+        self.status = match entries {
+            Option::None => String::from("The givens themselves have no solution"),
+            Some(entries) if entries.is_empty() => {
+                String::from("No conflicts, and the board can still be solved")
+            }
+            Some(entries) => {
+                self.hint_targets = Arc::new(entries.clone());
+                format!(
+                    "Remove {} to make the board solvable again",
+                    techniques::list(
+                        entries
+                            .iter()
+                            .map(|&index| techniques::cell_name(self.geometry(), index))
+                    )
+                )
+            }
+        };
+    }
+
+    fn cell_state(&self, index: usize) -> CellState {
+        CellState {
+            value: self.values[index],
+            notes: self.notes[index],
+            from_solver: self.from_solver[index],
+            eliminated: self.eliminated[index],
+        }
+    }
+
+    fn snapshot(&self) -> Vec<CellState> {
+        (0..self.values.len())
+            .map(|index| self.cell_state(index))
+            .collect()
+    }
+
+    // Everything that changed since `before` becomes a single undoable step.
+    fn record_edit(&mut self, before: Vec<CellState>) {
+        let edits: Vec<CellEdit> = before
+            .into_iter()
+            .enumerate()
+            .filter(|&(index, state)| state != self.cell_state(index))
+            .map(|(index, state)| CellEdit {
+                index,
+                before: state,
+                after: self.cell_state(index),
+            })
+            .collect();
+        if !edits.is_empty() {
+            Arc::make_mut(&mut self.undo_stack).push(edits);
+            self.redo_stack = Arc::new(Vec::new());
+        }
+    }
+
+    fn apply_cell_state(&mut self, index: usize, state: CellState) {
+        Arc::make_mut(&mut self.values)[index] = state.value;
+        Arc::make_mut(&mut self.notes)[index] = state.notes;
+        Arc::make_mut(&mut self.from_solver)[index] = state.from_solver;
+        Arc::make_mut(&mut self.eliminated)[index] = state.eliminated;
+    }
+
+    fn refresh_legality(&mut self) {
+        let is_legal = Arc::make_mut(&mut self.is_legal);
+        for (index, &value) in self.values.iter().enumerate() {
+            is_legal[index] = value == 0 || self.layout.is_legal_move(&self.values, index, value);
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(edits) = Arc::make_mut(&mut self.undo_stack).pop() {
+            for edit in edits.iter() {
+                self.apply_cell_state(edit.index, edit.before);
+            }
+            self.refresh_legality();
+            Arc::make_mut(&mut self.redo_stack).push(edits);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(edits) = Arc::make_mut(&mut self.redo_stack).pop() {
+            for edit in edits.iter() {
+                self.apply_cell_state(edit.index, edit.after);
+            }
+            self.refresh_legality();
+            Arc::make_mut(&mut self.undo_stack).push(edits);
+        }
+    }
+
+    fn toggle_note(&mut self, index: usize, value: u8) -> Vec<usize> {
+        let notes = Arc::make_mut(&mut self.notes);
+        if value == 0 {
+            notes[index] = 0;
+        } else if self.values[index] == 0 {
+            notes[index] ^= 1 << (value - 1);
+        }
+        vec![index]
+    }
+
+    fn set_value(&mut self, index: usize, value: u8) -> Vec<usize> {
+        let mut result: Vec<usize> = Vec::new();
+        let layout = self.layout.clone();
+        // Eliminations from earlier hints may have relied on the digit being replaced.
+        if self.values[index] != 0 && self.values[index] != value {
+            self.eliminated = Arc::new(vec![0; self.values.len()]);
+        }
+        Arc::make_mut(&mut self.values)[index] = value;
+        Arc::make_mut(&mut self.from_solver)[index] = false;
+        result.push(index);
+        if value != 0 {
+            result.append(&mut self.calculate_legality(index, value));
+            let bit = 1 << (value - 1);
+            let notes = Arc::make_mut(&mut self.notes);
+            notes[index] = 0;
+            for &peer in layout.peers[index].iter() {
+                if notes[peer] & bit != 0 {
+                    notes[peer] &= !bit;
+                    result.push(peer);
+                }
+            }
+        }
+        for &peer in layout.peers[index].iter() {
+            let peer_value = self.values[peer];
+            if value == 0 || peer_value != 0 {
+                result.append(&mut self.calculate_legality(peer, peer_value));
+            }
+        }
+        result
+    }
+
+    // A solve cancelled just as it finishes may still send its result, which is ignored as
+    // `cancel` has already put the board back in play.
+    fn solve_board(&mut self, sink: ExtEventSink, animate: bool) {
+        let mut board = self.values.as_ref().clone();
+        let layout = self.layout.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel_solve = Some(cancel.clone());
+        self.solving = true;
+        self.status = String::from("Solving...");
+        if animate {
+            self.animation = Some(self.values.clone());
+        }
+        let delay = if animate {
+            Some(self.animation_delay.clone())
+        } else {
+            Option::None
+        };
+        std::thread::spawn(move || {
+            let mut observer = SolveObserver {
+                sink: sink.clone(),
+                cancel,
+                last_report: Instant::now(),
+                delay,
+                guesses: Vec::new(),
+            };
+            if let Some(result) = solver::solve_observed(&layout, &mut board, &mut observer) {
+                sink.submit_command(
+                    SOLVE_COMPLETE,
+                    (observer.cancel, result, board),
+                    Target::Global,
+                )
+                .unwrap()
+            }
+        });
+    }
+
+    // Whether a message came from the solve still running, rather than one cancelled since.
+    fn is_current_solve(&self, cancel: &Arc<AtomicBool>) -> bool {
+        match &self.cancel_solve {
+            Some(current) => Arc::ptr_eq(current, cancel),
+            Option::None => false,
+        }
+    }
+
+    // Generating also takes a cancel flag, so loading another board in the meantime cancels it
+    // and the generated puzzle is ignored when it arrives.
+    fn start_generating(&mut self, status: String) -> Arc<AtomicBool> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel_solve = Some(cancel.clone());
+        self.solving = true;
+        self.status = status;
+        cancel
+    }
+
+    fn cancel(&mut self) {
+        if let Some(cancel) = self.cancel_solve.take() {
+            cancel.store(true, Ordering::Relaxed);
+            self.solving = false;
+            self.status = String::from("Solving cancelled");
+        }
+        self.animation = Option::None;
+        self.guesses = Arc::new(Vec::new());
+    }
+
+    // Halves or doubles the pause between animation steps.
+    fn change_animation_delay(&mut self, faster: bool) {
+        let delay = self.animation_delay.load(Ordering::Relaxed);
+        let delay = if faster { delay / 2 } else { delay * 2 };
+        let delay = delay
+            .max(*ANIMATION_DELAYS.start())
+            .min(*ANIMATION_DELAYS.end());
+        self.animation_delay.store(delay, Ordering::Relaxed);
+        self.status = format!("Animation step: {} ms", delay);
+    }
+
+    fn calculate_legality(&mut self, index: usize, value: u8) -> Vec<usize> {
+        let mut result: Vec<usize> = Vec::new();
+        let was_legal = self.is_legal[index];
+        if value > 0 {
+            let is_legal_now = self.layout.is_legal_move(&self.values, index, value);
+            let is_legal = Arc::make_mut(&mut self.is_legal);
+            is_legal[index] = is_legal_now;
+            if was_legal != is_legal_now {
+                result.push(index);
+            }
+            return result;
+        }
+        let is_legal = Arc::make_mut(&mut self.is_legal);
+        is_legal[index] = true;
+        if !was_legal {
+            result.push(index);
+        }
+        result
+    }
+}
+
+impl Square {
+    fn new(grid_index: usize) -> Self {
+        Square { grid_index }
+    }
+
+    // Diagonal lines for X-Sudoku, and dashed cage outlines with the sum in the cage's first cell.
+    // Jigsaw regions are outlined by the grid.
+    fn paint_rules(&self, ctx: &mut PaintCtx, data: &AppState) {
+        let geometry = data.geometry();
+        let variant = &data.layout.variant;
+        let size = geometry.size();
+        let cell = ctx.size().width;
+        let (row, col) = (
+            geometry.row_of(self.grid_index),
+            geometry.col_of(self.grid_index),
+        );
+        if variant.diagonals {
+            if row == col {
+                ctx.stroke(Line::new((0., 0.), (cell, cell)), &RULE_COLOR, 1.0);
+            }
+            if row + col == size - 1 {
+                ctx.stroke(Line::new((cell, 0.), (0., cell)), &RULE_COLOR, 1.0);
+            }
+        }
+        if let Some(cage) = variant.cage_of(self.grid_index) {
+            let dashes = StrokeStyle::new().dash(vec![3.0, 3.0], 0.0);
+            for line in borders(geometry, self.grid_index, cell, cell * 0.08, |other| {
+                cage.cells.contains(&other)
+            }) {
+                ctx.stroke_styled(line, &RULE_COLOR, 1.0, &dashes);
+            }
+            if cage.cells.iter().min() == Some(&self.grid_index) {
+                let layout = ctx
+                    .text()
+                    .new_text_layout(cage.sum.to_string())
+                    .font(FontFamily::MONOSPACE, cell * 0.22)
+                    .text_color(Color::BLACK)
+                    .build()
+                    .unwrap();
+                ctx.draw_text(&layout, (cell * 0.1, cell * 0.08));
+            }
+        }
+    }
+}
+
+// The cell edges, inset by `inset`, that face a cell outside the group or the edge of the board.
+fn borders(
+    geometry: Geometry,
+    index: usize,
+    cell: f64,
+    inset: f64,
+    same_group: impl Fn(usize) -> bool,
+) -> Vec<Line> {
+    let size = geometry.size();
+    let (row, col) = (geometry.row_of(index), geometry.col_of(index));
+    let (near, far) = (inset, cell - inset);
+    let mut lines = Vec::new();
+    if row == 0 || !same_group(index - size) {
+        lines.push(Line::new((near, near), (far, near)));
+    }
+    if col + 1 == size || !same_group(index + 1) {
+        lines.push(Line::new((far, near), (far, far)));
+    }
+    if row + 1 == size || !same_group(index + size) {
+        lines.push(Line::new((near, far), (far, far)));
+    }
+    if col == 0 || !same_group(index - 1) {
+        lines.push(Line::new((near, near), (near, far)));
+    }
+    lines
+}
+
+impl Widget<AppState> for Square {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, _env: &Env) {
+        match event {
+            Event::MouseDown(e) => {
+                ctx.request_focus();
+                data.select(self.grid_index, e.mods.shift() || e.mods.ctrl());
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            // Dragging with the button held sweeps cells into the selection.
+            Event::MouseMove(e)
+                if e.buttons.has_left() && !data.selection.contains(&self.grid_index) =>
+            {
+                Arc::make_mut(&mut data.selection).push(self.grid_index);
+                ctx.request_paint();
+            }
+            Event::KeyDown(e) => {
+                let geometry = data.geometry();
+                let action = match keys::action(e, geometry) {
+                    Some(action) if !data.solving && !data.paused => action,
+                    _ => return,
+                };
+                // Tab is the board's, rather than moving focus out of it.
+                ctx.set_handled();
+                match action {
+                    Action::Enter(value) => {
+                        for index in data.enter(value) {
+                            ctx.submit_command(Command::new(
+                                REPAINT,
+                                (),
+                                Target::Widget(data.widget_ids[index]),
+                            ));
+                        }
+                    }
+                    Action::ToggleNotes => data.notes_mode = !data.notes_mode,
+                    _ => {
+                        if let Some(target) = keys::target(
+                            action,
+                            geometry,
+                            self.grid_index,
+                            &data.values,
+                            data.settings.edge,
+                        ) {
+                            ctx.submit_command(Command::new(
+                                REQUEST_FOCUS,
+                                (),
+                                Target::Widget(data.widget_ids[target]),
+                            ));
+                        }
+                    }
+                }
+            }
+            Event::Command(c) => {
+                if let Some(_) = c.get(REQUEST_FOCUS) {
+                    ctx.request_focus();
+                    data.select(self.grid_index, false);
+                    ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                } else if let Some(_) = c.get(REPAINT) {
+                    ctx.request_paint();
+                } else if c.is(REVEAL_CELL) {
+                    if ctx.is_focused() && !data.solving && !data.paused && !data.solved_by_hand() {
+                        ctx.set_handled();
+                        for index in data.reveal(self.grid_index) {
+                            ctx.submit_command(Command::new(
+                                REPAINT,
+                                (),
+                                Target::Widget(data.widget_ids[index]),
+                            ));
+                        }
+                    }
+                } else if c.is(SOLVE) || c.is(WATCH_SOLVE) {
+                    ctx.set_handled();
+                    if data.finished || data.solving {
+                        return;
+                    }
+                    if data.is_legal.iter().all(|&x| x) {
+                        data.solve_board(ctx.get_external_handle(), c.is(WATCH_SOLVE));
+                        ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                    } else {
+                        data.status = String::from("Fix the conflicting cells before solving");
+                    }
+                } else if let Some((cancel, result, board)) = c.get(SOLVE_COMPLETE) {
+                    if board.len() != data.values.len() || !data.is_current_solve(cancel) {
+                        return;
+                    }
+                    data.cancel_solve = Option::None;
+                    data.animation = Option::None;
+                    data.guesses = Arc::new(Vec::new());
+                    let before = data.snapshot();
+                    let from_solver = Arc::make_mut(&mut data.from_solver);
+                    for (index, &value) in board.iter().enumerate() {
+                        if data.values[index] == 0 && value != 0 {
+                            from_solver[index] = true;
+                        }
+                    }
+                    data.values = Arc::new(board.clone());
+                    data.record_edit(before);
+                    data.update_progress();
+                    data.status = String::from(match result {
+                        SolveResult::Unique => "Solved: the solution is unique",
+                        SolveResult::Multiple => "Solved: multiple solutions exist, showing one",
+                        SolveResult::None => "No solution exists for this puzzle",
+                    });
+                    data.solving = false;
+                    ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        _data: &AppState,
+        _env: &Env,
+    ) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                ctx.register_for_focus();
+            }
+            LifeCycle::FocusChanged(_) => {
+                ctx.request_paint();
+            }
+            _ => {}
+        }
+    }
+
+    // Highlights depend on the focused cell's digit and the settings, not just this cell.
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, _env: &Env) {
+        if !old_data.settings.same(&data.settings)
+            || old_data.focused_digit() != data.focused_digit()
+        {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &AppState,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, _env: &Env) {
+        let size = ctx.size();
+        let rect = Rect::from_origin_size(Point::ORIGIN, size);
+
+        let animation = data.animation.as_ref();
+        let color = if data.guesses.contains(&self.grid_index) {
+            &GUESS_COLOR
+        } else if data.solving && animation.is_none() {
+            &Color::GRAY
+        } else if data.paused {
+            &Color::WHITE
+        } else if ctx.has_focus() {
+            &Color::AQUA
+        } else if data.selection.contains(&self.grid_index) {
+            &SELECTION_COLOR
+        } else if data.hint_targets.contains(&self.grid_index) {
+            &HINT_TARGET_COLOR
+        } else if data.hint_cells.contains(&self.grid_index) {
+            &HINT_COLOR
+        } else if data.settings.highlight_digits
+            && data.focused_digit().is_some()
+            && data.focused_digit() == Some(data.values[self.grid_index])
+        {
+            &SAME_DIGIT_COLOR
+        } else if data.settings.highlight_units && data.is_related(self.grid_index) {
+            &RELATED_COLOR
+        } else {
+            &Color::WHITE
+        };
+
+        ctx.fill(rect, color);
+        // Nothing on the board shows while paused.
+        if data.paused {
+            return;
+        }
+        self.paint_rules(ctx, data);
+
+        let geometry = data.geometry();
+        let cell = size.width;
+        let value = data.values[self.grid_index];
+        // Digits the animated search has placed but not yet committed to.
+        let tentative = match animation {
+            Some(board) if value == 0 => board[self.grid_index],
+            _ => 0,
+        };
+        let value = value.max(tentative);
+        if value > 0 {
+            let given = data.given[self.grid_index];
+            let from_solver = data.from_solver[self.grid_index] || tentative > 0;
+            let layout = ctx
+                .text()
+                .new_text_layout(geometry.symbol(value).to_string())
+                .font(FontFamily::MONOSPACE, cell * 0.75)
+                .default_attribute(if given {
+                    FontWeight::BOLD
+                } else {
+                    FontWeight::NORMAL
+                })
+                .default_attribute(if from_solver {
+                    FontStyle::Italic
+                } else {
+                    FontStyle::Regular
+                })
+                .text_color(
+                    if !data.is_legal[self.grid_index] || data.is_wrong(self.grid_index) {
+                        Color::RED
+                    } else if given {
+                        Color::BLACK
+                    } else if tentative > 0 {
+                        TENTATIVE_COLOR
+                    } else if from_solver {
+                        SOLVER_COLOR
+                    } else {
+                        USER_COLOR
+                    },
+                )
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, centred(&layout, Point::new(cell / 2., cell / 2.)));
+        } else {
+            // Notes sit in a small grid shaped like a box, one position per symbol.
+            let notes = data.notes[self.grid_index];
+            let note_width = cell / geometry.box_cols as f64;
+            let note_height = cell / geometry.box_rows as f64;
+            let note_size = note_width.min(note_height) * 0.75;
+            let focused_digit = if data.settings.highlight_notes {
+                data.focused_digit()
+            } else {
+                Option::None
+            };
+            for note in geometry.digits() {
+                if notes & (1 << (note - 1)) != 0 {
+                    let highlighted = focused_digit == Some(note);
+                    let layout = ctx
+                        .text()
+                        .new_text_layout(geometry.symbol(note).to_string())
+                        .font(FontFamily::MONOSPACE, note_size)
+                        .default_attribute(if highlighted {
+                            FontWeight::BOLD
+                        } else {
+                            FontWeight::NORMAL
+                        })
+                        .text_color(if highlighted {
+                            USER_COLOR
+                        } else {
+                            Color::grey8(96)
+                        })
+                        .build()
+                        .unwrap();
+                    let position = (note - 1) as usize;
+                    let center = Point::new(
+                        ((position % geometry.box_cols) as f64 + 0.5) * note_width,
+                        ((position / geometry.box_cols) as f64 + 0.5) * note_height,
+                    );
+                    ctx.draw_text(&layout, centred(&layout, center));
+                }
+            }
+        }
+    }
+}
+
+// Where to draw a text layout so that it's centred on `center`.
+fn centred(layout: &impl TextLayout, center: Point) -> Point {
+    let size = layout.size();
+    Point::new(center.x - size.width / 2., center.y - size.height / 2.)
+}
+
+impl Grid {
+    fn new(geometry: Geometry, widget_ids: &[WidgetId]) -> Grid {
+        Grid {
+            size: geometry.size(),
+            squares: (0..geometry.cells())
+                .map(|index| {
+                    let square = IdentityWrapper::wrap(Square::new(index), widget_ids[index]);
+                    WidgetPod::new(Box::new(square) as Box<dyn Widget<AppState>>)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Widget<AppState> for Grid {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        for square in self.squares.iter_mut() {
+            square.event(ctx, event, data, env);
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        for square in self.squares.iter_mut() {
+            square.lifecycle(ctx, event, data, env);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &AppState, data: &AppState, env: &Env) {
+        for square in self.squares.iter_mut() {
+            square.update(ctx, data, env);
+        }
+    }
+
+    // The largest square that fits, in cells of a whole number of pixels so they're all alike.
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &AppState,
+        env: &Env,
+    ) -> Size {
+        let max = bc.max();
+        let side = [max.width, max.height]
+            .iter()
+            .copied()
+            .filter(|side| side.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        let side = if side.is_finite() { side } else { GRID_SIZE };
+        let cell = (side / self.size as f64).floor().max(1.);
+        let cell_bc = BoxConstraints::tight(Size::new(cell, cell));
+        for (index, square) in self.squares.iter_mut().enumerate() {
+            square.layout(ctx, &cell_bc, data, env);
+            let origin = Point::new(
+                (index % self.size) as f64 * cell,
+                (index / self.size) as f64 * cell,
+            );
+            square.set_origin(ctx, data, env, origin);
+        }
+        bc.constrain(Size::new(cell * self.size as f64, cell * self.size as f64))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        for square in self.squares.iter_mut() {
+            square.paint(ctx, data, env);
+        }
+        let size = self.size;
+        let cell = ctx.size().width / size as f64;
+        let thick = (cell / 16.).max(2.);
+        let regions = &data.layout.regions;
+        let mut thin_lines = Vec::new();
+        let mut thick_lines = Vec::new();
+        for index in 0..size * size {
+            let (row, col) = ((index / size) as f64, (index % size) as f64);
+            if index % size + 1 < size {
+                let line = Line::new(
+                    ((col + 1.) * cell, row * cell),
+                    ((col + 1.) * cell, (row + 1.) * cell),
+                );
+                if regions[index] == regions[index + 1] {
+                    thin_lines.push(line);
+                } else {
+                    thick_lines.push(line);
+                }
+            }
+            if index / size + 1 < size {
+                let line = Line::new(
+                    (col * cell, (row + 1.) * cell),
+                    ((col + 1.) * cell, (row + 1.) * cell),
+                );
+                if regions[index] == regions[index + size] {
+                    thin_lines.push(line);
+                } else {
+                    thick_lines.push(line);
+                }
+            }
+        }
+        for line in thin_lines {
+            ctx.stroke(line, &Color::BLACK, 1.0);
+        }
+        for line in thick_lines {
+            ctx.stroke(line, &Color::BLACK, thick);
+        }
+        let outline = ctx.size().to_rect().inset(-thick / 2.);
+        ctx.stroke(outline, &Color::BLACK, thick);
+    }
+}
+
+// Buttons for each symbol, erase and notes mode. The larger boards split the symbols over two rows.
+fn build_pad(geometry: Geometry) -> Flex<AppState> {
+    let size = geometry.size();
+    let per_row = if size > 9 { size / 2 } else { size };
+    let digits: Vec<u8> = geometry.digits().collect();
+    let mut result = Flex::column();
+    let mut rows = digits.chunks(per_row).peekable();
+    while let Some(values) = rows.next() {
+        let mut row = Flex::row();
+        for &value in values {
+            row.add_child(pad_button(geometry.symbol(value).to_string(), value).fix_width(32.));
+        }
+        if rows.peek().is_some() {
+            result.add_child(row);
+            result.add_spacer(4.);
+            continue;
+        }
+        row.add_spacer(8.);
+        row.add_child(pad_button(String::from("Erase"), 0));
+        row.add_child(
+            Button::new("Notes").on_click(|_ctx, data: &mut AppState, _env| {
+                data.notes_mode = !data.notes_mode;
+            }),
+        );
+        result.add_child(row);
+    }
+    result
+}
+
+fn pad_button(label: String, value: u8) -> impl Widget<AppState> {
+    Button::new(label).on_click(move |ctx, data: &mut AppState, _env| {
+        for index in data.enter(value) {
+            ctx.submit_command(Command::new(
+                REPAINT,
+                (),
+                Target::Widget(data.widget_ids[index]),
+            ));
+        }
+    })
+}
+
+struct Delegate {
+    sink: ExtEventSink,
+    // Set while the save panel for a booklet is open, so the chosen file gets one.
+    booklet: bool,
+}
+
+impl AppDelegate<AppState> for Delegate {
+    fn command(
+        &mut self,
+        ctx: &mut DelegateCtx,
+        target: Target,
+        cmd: &Command,
+        data: &mut AppState,
+        _env: &Env,
+    ) -> Handled {
+        if let Some(file_info) = cmd.get(druid::commands::SAVE_FILE_AS) {
+            let board = Board {
+                geometry: data.geometry(),
+                variant: data.layout.variant.clone(),
+                values: data.values.as_ref().clone(),
+            };
+            let path = file_info.path().to_path_buf();
+            if self.booklet {
+                self.booklet = false;
+                if data.solving {
+                    return Handled::Yes;
+                }
+                let cancel = data.start_generating(String::from("Generating booklet..."));
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                let sink = self.sink.clone();
+                let difficulty = data.difficulty.unwrap_or(Difficulty::Medium);
+                std::thread::spawn(move || {
+                    let puzzles: Option<Vec<Puzzle>> = (0..BOOKLET_PUZZLES)
+                        .map(|_| {
+                            generator::generate(board.geometry, &board.variant, difficulty, &cancel)
+                        })
+                        .collect();
+                    let result = match puzzles {
+                        Some(puzzles) => {
+                            let pages = print::booklet(&puzzles);
+                            std::fs::write(&path, print::to_pdf(&pages))
+                                .map(|_| pages.len())
+                                .map_err(|e| e.to_string())
+                        }
+                        Option::None => Err(String::from(NO_GRID)),
+                    };
+                    sink.submit_command(BOOKLET_EXPORTED, (cancel, result), Target::Global)
+                        .unwrap()
+                });
+                return Handled::Yes;
+            }
+            if has_extension(&path, "png")
+                || has_extension(&path, "svg")
+                || has_extension(&path, "pdf")
+            {
+                let notes = if data.settings.export_notes {
+                    Some(data.notes.as_slice())
+                } else {
+                    Option::None
+                };
+                let page = print::board_page(&board, &data.given, notes);
+                let result = if has_extension(&path, "png") {
+                    export::to_png(&page, &path)
+                } else if has_extension(&path, "svg") {
+                    std::fs::write(&path, print::to_svg(&page)).map_err(|e| e.to_string())
+                } else {
+                    std::fs::write(&path, print::to_pdf(&[page])).map_err(|e| e.to_string())
+                };
+                match result {
+                    Ok(()) => data.status = String::from("Board exported"),
+                    Err(e) => ctx.new_window(error_dialog(format!("Error exporting board: {}", e))),
+                }
+                return Handled::Yes;
+            }
+            let contents = if has_extension(file_info.path(), "json") {
+                Session::capture(data).to_json()
+            } else if has_extension(file_info.path(), "sdk") || !board.variant.is_classic() {
+                formats::to_sdk(&board)
+            } else {
+                formats::to_line(&board)
+            };
+            if let Err(e) = std::fs::write(&path, contents) {
+                ctx.new_window(error_dialog(format!("Error writing file: {}", e)));
+            }
+            return Handled::Yes;
+        }
+        if let Some(file_info) = cmd.get(druid::commands::OPEN_FILE) {
+            if is_image(file_info.path()) {
+                match image::open(file_info.path()) {
+                    Ok(image) => match recognize::recognize(&image) {
+                        Ok(board) => {
+                            data.import(&board);
+                            ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                        }
+                        Err(e) => ctx.new_window(error_dialog(format!("Can't import: {}", e))),
+                    },
+                    Err(e) => ctx.new_window(error_dialog(format!("Error opening image: {}", e))),
+                }
+                return Handled::Yes;
+            }
+            match std::fs::read_to_string(file_info.path()) {
+                Ok(s) if has_extension(file_info.path(), "json") => match Session::from_json(&s) {
+                    Ok(session) => {
+                        session.restore(data);
+                        data.find_solution(self.sink.clone());
+                        data.status = String::from("Session restored");
+                        ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                    }
+                    Err(e) => ctx.new_window(error_dialog(format!("Can't load session: {}", e))),
+                },
+                Ok(s) => match formats::parse(&s) {
+                    Ok(mut puzzles) if puzzles.len() == 1 => {
+                        ctx.submit_command(Command::new(
+                            LOAD_PUZZLE,
+                            (0, puzzles.remove(0)),
+                            Target::Global,
+                        ));
+                    }
+                    Ok(puzzles) => ctx.new_window(puzzle_picker(puzzles)),
+                    Err(e) => ctx.new_window(error_dialog(format!("Can't read puzzle: {}", e))),
+                },
+                Err(e) => {
+                    ctx.new_window(error_dialog(format!("Error opening file: {}", e)));
+                }
+            }
+            return Handled::Yes;
+        }
+        if let Some((number, board)) = cmd.get(LOAD_PUZZLE) {
+            data.load_board(board);
+            data.find_solution(self.sink.clone());
+            data.status = if *number > 0 {
+                format!("Loaded puzzle {}", number)
+            } else {
+                String::new()
+            };
+            ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            return Handled::Yes;
+        }
+        if let Some(&geometry) = cmd.get(SET_GEOMETRY) {
+            if !data.solving {
+                data.load_board(&Board::empty(geometry));
+                data.status = format!("Empty {} board", geometry.name());
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if let Some(&toggle) = cmd.get(TOGGLE_RULE) {
+            if !data.solving && !data.solved_by_hand() {
+                let mut variant = data.layout.variant.clone();
+                variant.toggle(toggle);
+                data.status = format!(
+                    "{} {}",
+                    toggle.name(),
+                    if variant.is_set(toggle) { "on" } else { "off" }
+                );
+                data.set_variant(variant);
+                data.find_solution(self.sink.clone());
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(druid::commands::UNDO) || cmd.is(druid::commands::REDO) {
+            if !data.solving && !data.solved_by_hand() {
+                if cmd.is(druid::commands::UNDO) {
+                    data.undo();
+                } else {
+                    data.redo();
+                }
+                data.update_progress();
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(HINT) {
+            if !data.solving && !data.solved_by_hand() {
+                data.hint();
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(EXPLAIN_CONFLICTS) {
+            if !data.solving {
+                let explanations = data.explain_conflicts(self.sink.clone());
+                if explanations.len() > 1 {
+                    ctx.new_window(conflicts_window(explanations));
+                }
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if let Some((board, entries)) = cmd.get(ENTRIES_TO_REMOVE) {
+            // Only worth showing if the board hasn't been changed since.
+            if *board == *data.values {
+                data.show_entries_to_remove(entries);
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(TOGGLE_PAUSE) {
+            if !data.finished {
+                data.paused = !data.paused;
+                data.status = String::from(if data.paused { "Paused" } else { "" });
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(CANCEL_SOLVE) {
+            if data.cancel_solve.is_some() {
+                data.cancel();
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if let Some((cancel, board, guesses)) = cmd.get(ANIMATION_STEP) {
+            if data.is_current_solve(cancel) && data.animation.is_some() {
+                data.animation = Some(Arc::new(board.clone()));
+                data.guesses = Arc::new(guesses.clone());
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(ANIMATION_FASTER) || cmd.is(ANIMATION_SLOWER) {
+            data.change_animation_delay(cmd.is(ANIMATION_FASTER));
+            return Handled::Yes;
+        }
+        if let Some((cancel, nodes, depth)) = cmd.get(SOLVE_PROGRESS) {
+            if data.is_current_solve(cancel) {
+                data.status = format!(
+                    "Solving... {} positions tried, {} guesses deep",
+                    nodes, depth
+                );
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(TOGGLE_CHECK) {
+            data.check_mode = !data.check_mode;
+            if data.check_mode && data.solution.is_none() && data.given.contains(&true) {
+                data.status = String::from("Entries will be checked once the puzzle is solved");
+            }
+            ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            return Handled::Yes;
+        }
+        if let Some((puzzle, solution)) = cmd.get(SOLUTION_FOUND) {
+            // The player may have moved on to another puzzle in the meantime.
+            if *puzzle == data.puzzle() && data.solution.is_none() {
+                data.solution = solution.clone().map(Arc::new);
+                if data.check_mode {
+                    if data.solution.is_none() {
+                        data.status =
+                            String::from("Can't check entries: the puzzle has no unique solution");
+                    }
+                    ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                }
+            }
+            return Handled::Yes;
+        }
+        if let Some((puzzle, grade)) = cmd.get(PUZZLE_GRADED) {
+            if *puzzle == data.puzzle() && data.difficulty.is_none() {
+                data.difficulty = Some(*grade);
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(CONFIRM_IMPORT) {
+            if data.importing {
+                data.load_board(&Board {
+                    geometry: data.geometry(),
+                    variant: data.layout.variant.clone(),
+                    values: data.values.as_ref().clone(),
+                });
+                data.find_solution(self.sink.clone());
+                data.status = String::from("Imported puzzle ready");
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(EXPORT_BOOKLET) {
+            if let Target::Window(id) = target {
+                self.booklet = true;
+                ctx.submit_command(Command::new(
+                    druid::commands::SHOW_SAVE_PANEL,
+                    booklet_dialog_options(),
+                    Target::Window(id),
+                ));
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(druid::commands::SAVE_PANEL_CANCELLED) {
+            self.booklet = false;
+            return Handled::No;
+        }
+        if let Some((cancel, result)) = cmd.get(BOOKLET_EXPORTED) {
+            if !data.is_current_solve(cancel) {
+                return Handled::Yes;
+            }
+            data.cancel_solve = Option::None;
+            data.solving = false;
+            ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            match result {
+                Ok(pages) => data.status = format!("Booklet of {} pages exported", pages),
+                Err(e) => {
+                    data.status = String::from("Booklet not exported");
+                    ctx.new_window(error_dialog(format!("Error exporting booklet: {}", e)));
+                }
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(SHOW_SETTINGS) {
+            ctx.new_window(settings_window());
+            return Handled::Yes;
+        }
+        if cmd.is(SHOW_STATS) {
+            ctx.new_window(stats_window(Stats::load(), History::load()));
+            return Handled::Yes;
+        }
+        if cmd.is(TOGGLE_NOTES) {
+            data.notes_mode = !data.notes_mode;
+            return Handled::Yes;
+        }
+        if let Some(&difficulty) = cmd.get(NEW_PUZZLE) {
+            if !data.solving {
+                let cancel =
+                    data.start_generating(format!("Generating {} puzzle...", difficulty.name()));
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                let sink = self.sink.clone();
+                let geometry = data.geometry();
+                let variant = data.layout.variant.clone();
+                std::thread::spawn(move || {
+                    let puzzle = generator::generate(geometry, &variant, difficulty, &cancel);
+                    sink.submit_command(PUZZLE_GENERATED, (cancel, puzzle), Target::Global)
+                        .unwrap()
+                });
+            }
+            return Handled::Yes;
+        }
+        if cmd.is(DAILY_PUZZLE) {
+            if !data.solving {
+                let cancel = data.start_generating(String::from("Generating the daily puzzle..."));
+                ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+                let sink = self.sink.clone();
+                let day = daily::today();
+                std::thread::spawn(move || {
+                    let puzzle = daily::puzzle(day);
+                    sink.submit_command(DAILY_GENERATED, (cancel, day, puzzle), Target::Global)
+                        .unwrap()
+                });
+            }
+            return Handled::Yes;
+        }
+        if let Some((cancel, day, puzzle)) = cmd.get(DAILY_GENERATED) {
+            if !data.is_current_solve(cancel) {
+                return Handled::Yes;
+            }
+            data.cancel_solve = Option::None;
+            data.load_board(&puzzle.board);
+            data.difficulty = Some(puzzle.difficulty);
+            data.solution = Some(Arc::new(puzzle.solution.clone()));
+            data.daily = Some(*day);
+            data.solving = false;
+            data.status = if History::load().days.contains_key(day) {
+                format!("Daily puzzle for {}, already solved", daily::date(*day))
+            } else {
+                format!("Daily puzzle for {}", daily::date(*day))
+            };
+            ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            return Handled::Yes;
+        }
+        if cmd.is(COPY_DAILY_SUMMARY) {
+            match History::load().summary(daily::today()) {
+                Some(summary) => {
+                    Application::global().clipboard().put_string(summary);
+                    data.status = String::from("Daily summary copied");
+                }
+                Option::None => data.status = String::from("Today's daily puzzle isn't solved yet"),
+            }
+            return Handled::Yes;
+        }
+        if let Some((cancel, puzzle)) = cmd.get(PUZZLE_GENERATED) {
+            if !data.is_current_solve(cancel) {
+                return Handled::Yes;
+            }
+            data.cancel_solve = Option::None;
+            data.solving = false;
+            let puzzle = match puzzle {
+                Some(puzzle) => puzzle,
+                Option::None => {
+                    data.status = String::from(NO_GRID);
+                    return Handled::Yes;
+                }
+            };
+            data.load_board(&puzzle.board);
+            data.difficulty = Some(puzzle.difficulty);
+            data.solution = Some(Arc::new(puzzle.solution.clone()));
+            data.status = format!("New {} puzzle", puzzle.difficulty.name());
+            ctx.submit_command(Command::new(REPAINT, (), Target::Global));
+            return Handled::Yes;
+        }
+        Handled::No
+    }
+}
+
+// Passes solver progress to the UI, at most every PROGRESS_INTERVAL so the event queue isn't
+// flooded. When animating, every guess and backtrack is sent too, pausing for the delay after each.
+struct SolveObserver {
+    sink: ExtEventSink,
+    cancel: Arc<AtomicBool>,
+    last_report: Instant,
+    delay: Option<Arc<AtomicU64>>,
+    guesses: Vec<usize>,
+}
+
+impl SolveObserver {
+    fn show(&mut self, board: &[u8]) {
+        if let Some(delay) = &self.delay {
+            if self.cancelled() {
+                return;
+            }
+            self.sink
+                .submit_command(
+                    ANIMATION_STEP,
+                    (self.cancel.clone(), board.to_vec(), self.guesses.clone()),
+                    Target::Global,
+                )
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(delay.load(Ordering::Relaxed)));
+        }
+    }
+}
+
+impl Observer for SolveObserver {
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn progress(&mut self, nodes: u64, depth: usize) {
+        if self.last_report.elapsed() >= PROGRESS_INTERVAL && !self.cancelled() {
+            self.last_report = Instant::now();
+            self.sink
+                .submit_command(
+                    SOLVE_PROGRESS,
+                    (self.cancel.clone(), nodes, depth),
+                    Target::Global,
+                )
+                .unwrap();
+        }
+    }
+
+    fn guessed(&mut self, board: &[u8], index: usize) {
+        self.guesses.push(index);
+        self.show(board);
+    }
+
+    fn backtracked(&mut self, board: &[u8], _index: usize) {
+        self.guesses.pop();
+        self.show(board);
+    }
+}
+
+// Advances the game clock on timer ticks, measuring real time between ticks so it doesn't drift.
+struct GameClock {
+    timer: TimerToken,
+    last_tick: Instant,
+}
+
+impl GameClock {
+    fn new() -> Self {
+        GameClock {
+            timer: TimerToken::INVALID,
+            last_tick: Instant::now(),
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for GameClock {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Timer(token) if *token == self.timer => {
+                let now = Instant::now();
+                if data.is_clock_running() {
+                    data.elapsed += now.duration_since(self.last_tick).as_secs_f64();
+                }
+                self.last_tick = now;
+                self.timer = ctx.request_timer(CLOCK_TICK);
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.last_tick = Instant::now();
+            self.timer = ctx.request_timer(CLOCK_TICK);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+// Puzzles for a booklet only go to PDF, where they can be laid out over several pages.
+fn booklet_dialog_options() -> FileDialogOptions {
+    let pdf = FileSpec::new("PDF document", &["pdf"]);
+    FileDialogOptions::new()
+        .allowed_types(vec![pdf])
+        .default_type(pdf)
+        .default_name("sudoku_booklet.pdf")
+        .name_label("Target")
+        .title("Print a booklet of new puzzles")
+        .button_text("Export")
+}
+
+fn is_image(path: &Path) -> bool {
+    has_extension(path, "png") || has_extension(path, "jpg") || has_extension(path, "jpeg")
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    match path.extension() {
+        Some(actual) => actual.eq_ignore_ascii_case(extension),
+        Option::None => false,
+    }
+}
+
+fn error_dialog(message: String) -> WindowDesc<AppState> {
+    WindowDesc::new(move || {
+        Flex::column()
+            .with_child(
+                Label::new(message)
+                    .with_line_break_mode(LineBreaking::WordWrap)
+                    .expand_width(),
+            )
+            .with_spacer(16.)
+            .with_child(
+                Button::new("OK").on_click(|ctx, _data: &mut AppState, _env| {
+                    ctx.submit_command(Command::new(
+                        druid::commands::CLOSE_WINDOW,
+                        (),
+                        Target::Window(ctx.window_id()),
+                    ))
+                }),
+            )
+            .padding(16.)
+    })
+    .window_size((420., 160.))
+    .resizable(false)
+    .title("Error")
+}
+
+fn conflicts_window(explanations: Vec<String>) -> WindowDesc<AppState> {
+    WindowDesc::new(move || {
+        let mut list = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+        for explanation in explanations.iter() {
+            list.add_child(
+                Label::new(explanation.clone())
+                    .with_line_break_mode(LineBreaking::WordWrap)
+                    .expand_width(),
+            );
+        }
+        Flex::column()
+            .with_flex_child(Scroll::new(list).vertical().expand_width(), 1.0)
+            .with_spacer(8.)
+            .with_child(
+                Button::new("OK").on_click(|ctx, _data: &mut AppState, _env| {
+                    ctx.submit_command(Command::new(
+                        druid::commands::CLOSE_WINDOW,
+                        (),
+                        Target::Window(ctx.window_id()),
+                    ))
+                }),
+            )
+            .padding(16.)
+    })
+    .window_size((420., 300.))
+    .title("Conflicts")
+}
+
+// Lists every puzzle in a multi-puzzle file; choosing one loads it and closes the picker.
+fn puzzle_picker(puzzles: Vec<Board>) -> WindowDesc<AppState> {
+    WindowDesc::new(move || {
+        let mut list = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+        for (index, puzzle) in puzzles.iter().enumerate() {
+            let clues = puzzle.values.iter().filter(|&&value| value != 0).count();
+            let puzzle = puzzle.clone();
+            list.add_child(
+                Button::new(format!(
+                    "Puzzle {} ({}, {} clues)",
+                    index + 1,
+                    puzzle.geometry.name(),
+                    clues
+                ))
+                .on_click(move |ctx, _data: &mut AppState, _env| {
+                    ctx.submit_command(Command::new(
+                        LOAD_PUZZLE,
+                        (index + 1, puzzle.clone()),
+                        Target::Global,
+                    ));
+                    ctx.submit_command(Command::new(
+                        druid::commands::CLOSE_WINDOW,
+                        (),
+                        Target::Window(ctx.window_id()),
+                    ));
+                })
+                .padding(2.),
+            );
+        }
+        Scroll::new(list).vertical().padding(8.)
+    })
+    .window_size((260., 400.))
+    .title("Choose a puzzle")
+}
+
+fn stats_window(stats: Stats, history: History) -> WindowDesc<AppState> {
+    WindowDesc::new(move || {
+        let mut list = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+        if !history.days.is_empty() {
+            list.add_child(Label::new(format!(
+                "Daily puzzle: streak {}, longest {}, {} solved",
+                history.current_streak(daily::today()),
+                history.longest_streak(),
+                history.days.len()
+            )));
+            list.add_spacer(8.);
+        }
+        for &geometry in SIZES.iter() {
+            for &difficulty in [
+                Difficulty::Easy,
+                Difficulty::Medium,
+                Difficulty::Hard,
+                Difficulty::Expert,
+            ]
+            .iter()
+            {
+                let key = stats::key(geometry, difficulty);
+                if let Some(&best) = stats.best_times.get(&key) {
+                    let solved = stats.solved.get(&key).copied().unwrap_or(0);
+                    list.add_child(Label::new(format!(
+                        "{}: best {}, {} solved",
+                        key,
+                        stats::format_time(best),
+                        solved
+                    )));
+                }
+            }
+        }
+        if stats.best_times.is_empty() {
+            list.add_child(Label::new("No puzzles solved yet"));
+        }
+        Flex::column()
+            .with_flex_child(Scroll::new(list).vertical().expand_width(), 1.0)
+            .with_spacer(8.)
+            .with_child(
+                Button::new("OK").on_click(|ctx, _data: &mut AppState, _env| {
+                    ctx.submit_command(Command::new(
+                        druid::commands::CLOSE_WINDOW,
+                        (),
+                        Target::Window(ctx.window_id()),
+                    ))
+                }),
+            )
+            .padding(16.)
+    })
+    .window_size((320., 300.))
+    .title("Statistics")
+}
+
+// Changes show on the board straight away; they're written to disk when the window is closed
+// with OK.
+fn settings_window() -> WindowDesc<AppState> {
+    WindowDesc::new(|| {
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Checkbox::new("Highlight the focused cell's row, column and box")
+                    .lens(AppState::settings.then(Settings::highlight_units)),
+            )
+            .with_spacer(8.)
+            .with_child(
+                Checkbox::new("Highlight cells with the same digit")
+                    .lens(AppState::settings.then(Settings::highlight_digits)),
+            )
+            .with_spacer(8.)
+            .with_child(
+                Checkbox::new("Highlight notes for the same digit")
+                    .lens(AppState::settings.then(Settings::highlight_notes)),
+            )
+            .with_spacer(8.)
+            .with_child(
+                Checkbox::new("Include notes when exporting the board")
+                    .lens(AppState::settings.then(Settings::export_notes)),
+            )
+            .with_spacer(8.)
+            .with_child(Label::new("At the edge of the board, the arrow keys"))
+            .with_child(
+                RadioGroup::new(vec![
+                    ("stop", Edge::Stop),
+                    ("wrap round to the same row or column", Edge::Wrap),
+                    ("carry on into the next row or column", Edge::Continue),
+                ])
+                .lens(AppState::settings.then(Settings::edge)),
+            )
+            .with_flex_spacer(1.0)
+            .with_child(
+                Button::new("OK").on_click(|ctx, data: &mut AppState, _env| {
+                    if let Err(e) = data.settings.save() {
+                        data.status = format!("Couldn't save settings: {}", e);
+                    }
+                    ctx.submit_command(Command::new(
+                        druid::commands::CLOSE_WINDOW,
+                        (),
+                        Target::Window(ctx.window_id()),
+                    ))
+                }),
+            )
+            .padding(16.)
+    })
+    .window_size((400., 340.))
+    .title("Settings")
+}
+
+fn board_size_item(geometry: Geometry) -> MenuItem<AppState> {
+    MenuItem::new(
+        LocalizedString::new("board-size").with_placeholder(geometry.name()),
+        Command::new(SET_GEOMETRY, geometry, Target::Global),
+    )
+}
+
+fn rule_item(toggle: Toggle) -> MenuItem<AppState> {
+    MenuItem::new(
+        LocalizedString::new("rule").with_placeholder(toggle.name()),
+        Command::new(TOGGLE_RULE, toggle, Target::Global),
+    )
+}
+
+fn new_puzzle_item(difficulty: Difficulty) -> MenuItem<AppState> {
+    MenuItem::new(
+        LocalizedString::new(difficulty.name()),
+        Command::new(NEW_PUZZLE, difficulty, Target::Global),
+    )
+}
+
+pub fn main() {
+    let app_state = AppState::new(Geometry::CLASSIC);
+
+    let txt = FileSpec::new("Text file", &["txt"]);
+    let sdk = FileSpec::new("SadMan Sudoku", &["sdk"]);
+    // The options can also be generated at runtime,
+    // so to show that off we create a String for the default save name.
+    let default_save_name = String::from("sudodu_puzzle.txt");
+    let save_dialog_options = FileDialogOptions::new()
+        .allowed_types(vec![txt, sdk])
+        .default_type(txt)
+        .default_name(default_save_name)
+        .name_label("Target")
+        .title("Choose a target for this lovely file")
+        .button_text("Export");
+
+    let open_dialog_options = save_dialog_options
+        .clone()
+        .default_name("MySavedFile.txt")
+        .name_label("Source")
+        .title("Where did you put that file?")
+        .button_text("Import");
+
+    let session = FileSpec::new("Sudoku session", &["json"]);
+    let save_session_options = FileDialogOptions::new()
+        .allowed_types(vec![session])
+        .default_type(session)
+        .default_name("sudoku_session.json")
+        .name_label("Target")
+        .title("Save the current game")
+        .button_text("Save");
+
+    let open_session_options = save_session_options
+        .clone()
+        .name_label("Source")
+        .title("Resume a saved game")
+        .button_text("Load");
+
+    let png = FileSpec::new("PNG image", &["png"]);
+    let svg = FileSpec::new("SVG image", &["svg"]);
+    let pdf = FileSpec::new("PDF document", &["pdf"]);
+    let export_dialog_options = FileDialogOptions::new()
+        .allowed_types(vec![png, svg, pdf])
+        .default_type(pdf)
+        .default_name("sudoku_board.pdf")
+        .name_label("Target")
+        .title("Export the board for printing")
+        .button_text("Export");
+
+    let image = FileSpec::new("Image", &["png", "jpg", "jpeg"]);
+    let import_image_options = FileDialogOptions::new()
+        .allowed_types(vec![image])
+        .default_type(image)
+        .name_label("Source")
+        .title("Read a puzzle from a picture")
+        .button_text("Import");
+
+    let launcher = AppLauncher::with_window(
+        WindowDesc::new(move || {
+            Flex::column()
+                .with_flex_child(
+                    ViewSwitcher::new(
+                        |data: &AppState, _env| data.grid_shape(),
+                        |_, data: &AppState, _env| {
+                            Box::new(
+                                Flex::column()
+                                    .with_flex_child(
+                                        Grid::new(data.geometry(), &data.widget_ids),
+                                        1.0,
+                                    )
+                                    .with_spacer(6.)
+                                    .with_child(build_pad(data.geometry())),
+                            )
+                        },
+                    ),
+                    1.0,
+                )
+                .with_child(
+                    Flex::row()
+                        .with_flex_child(
+                            // A finished puzzle gets its result shown as a banner.
+                            Either::new(
+                                |data: &AppState, _env| data.solved_by_hand(),
+                                Label::dynamic(|data: &AppState, _| data.status.clone())
+                                    .with_text_size(16.)
+                                    .with_text_color(Color::BLACK)
+                                    .padding((4., 0.))
+                                    .expand_width()
+                                    .background(BANNER_COLOR)
+                                    .rounded(4.),
+                                Label::dynamic(|data: &AppState, _| data.status.clone())
+                                    .with_text_size(16.)
+                                    .expand_width(),
+                            ),
+                            1.0,
+                        )
+                        .with_child(
+                            Label::dynamic(|data: &AppState, _| stats::format_time(data.elapsed))
+                                .with_text_size(16.)
+                                .controller(GameClock::new()),
+                        )
+                        .with_spacer(8.)
+                        .with_child(
+                            Label::dynamic(|data: &AppState, _| {
+                                let mut labels: Vec<String> = data
+                                    .layout
+                                    .variant
+                                    .names()
+                                    .into_iter()
+                                    .map(String::from)
+                                    .collect();
+                                if data.check_mode {
+                                    labels.push(format!("Mistakes: {}", data.mistakes));
+                                }
+                                if data.notes_mode {
+                                    labels.push(String::from("Notes"));
+                                }
+                                labels.join(" · ")
+                            })
+                            .with_text_size(16.),
+                        ),
+                )
+        })
+        .window_size((460., 600.))
+        .with_min_size((320., 440.))
+        .title(LocalizedString::new("app-title").with_placeholder("Sudoku"))
+        .menu(
+            MenuDesc::empty()
+                .append(
+                    MenuDesc::new(
+                        LocalizedString::new("new-puzzle").with_placeholder("New Puzzle"),
+                    )
+                    .append(new_puzzle_item(Difficulty::Easy))
+                    .append(new_puzzle_item(Difficulty::Medium))
+                    .append(new_puzzle_item(Difficulty::Hard))
+                    .append(new_puzzle_item(Difficulty::Expert))
+                    .append_separator()
+                    .append(MenuItem::new(
+                        LocalizedString::new("daily-puzzle").with_placeholder("Daily Puzzle"),
+                        Command::new(DAILY_PUZZLE, (), Target::Global),
+                    ))
+                    .append(MenuItem::new(
+                        LocalizedString::new("copy-daily-summary")
+                            .with_placeholder("Copy Daily Summary"),
+                        Command::new(COPY_DAILY_SUMMARY, (), Target::Global),
+                    )),
+                )
+                .append(SIZES.iter().fold(
+                    MenuDesc::new(
+                        LocalizedString::new("board-size").with_placeholder("Board Size"),
+                    ),
+                    |menu, &geometry| menu.append(board_size_item(geometry)),
+                ))
+                .append(
+                    MenuDesc::new(LocalizedString::new("rules").with_placeholder("Rules"))
+                        .append(rule_item(Toggle::Diagonals))
+                        .append(rule_item(Toggle::AntiKnight))
+                        .append(rule_item(Toggle::AntiKing)),
+                )
+                .append(
+                    MenuDesc::new(LocalizedString::new("common-menu-edit-menu"))
+                        .append(druid::platform_menus::common::undo())
+                        .append(
+                            MenuItem::new(
+                                LocalizedString::new("common-menu-redo"),
+                                druid::commands::REDO,
+                            )
+                            .hotkey(SysMods::CmdShift, "Z"),
+                        ),
+                )
+                .append(MenuItem::new(
+                    LocalizedString::new("pause").with_placeholder("Pause"),
+                    Command::new(TOGGLE_PAUSE, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("statistics").with_placeholder("Statistics"),
+                    Command::new(SHOW_STATS, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("settings").with_placeholder("Settings"),
+                    Command::new(SHOW_SETTINGS, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("Hint"),
+                    Command::new(HINT, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("explain-conflicts").with_placeholder("Explain Conflicts"),
+                    Command::new(EXPLAIN_CONFLICTS, (), Target::Global),
+                ))
+                .append(
+                    MenuDesc::new(LocalizedString::new("animate").with_placeholder("Animate"))
+                        .append(MenuItem::new(
+                            LocalizedString::new("watch-solve").with_placeholder("Watch Solve"),
+                            Command::new(WATCH_SOLVE, (), Target::Global),
+                        ))
+                        .append(MenuItem::new(
+                            LocalizedString::new("animate-faster").with_placeholder("Faster"),
+                            Command::new(ANIMATION_FASTER, (), Target::Global),
+                        ))
+                        .append(MenuItem::new(
+                            LocalizedString::new("animate-slower").with_placeholder("Slower"),
+                            Command::new(ANIMATION_SLOWER, (), Target::Global),
+                        )),
+                )
+                .append(MenuItem::new(
+                    LocalizedString::new("cancel-solve").with_placeholder("Cancel Solve"),
+                    Command::new(CANCEL_SOLVE, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("check-mode").with_placeholder("Check Against Solution"),
+                    Command::new(TOGGLE_CHECK, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("reveal-cell").with_placeholder("Reveal Cell"),
+                    Command::new(REVEAL_CELL, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("Solve"),
+                    Command::new(SOLVE, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("notes-mode").with_placeholder("Notes"),
+                    Command::new(TOGGLE_NOTES, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("Load"),
+                    Command::new(
+                        druid::commands::SHOW_OPEN_PANEL,
+                        open_dialog_options.clone(),
+                        Target::Auto,
+                    ),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("import-image").with_placeholder("Import from Image"),
+                    Command::new(
+                        druid::commands::SHOW_OPEN_PANEL,
+                        import_image_options,
+                        Target::Auto,
+                    ),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("confirm-import").with_placeholder("Confirm Import"),
+                    Command::new(CONFIRM_IMPORT, (), Target::Global),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("Save"),
+                    Command::new(
+                        druid::commands::SHOW_SAVE_PANEL,
+                        save_dialog_options.clone(),
+                        Target::Auto,
+                    ),
+                ))
+                .append(
+                    MenuDesc::new(LocalizedString::new("export").with_placeholder("Export"))
+                        .append(MenuItem::new(
+                            LocalizedString::new("export-board").with_placeholder("Export Board"),
+                            Command::new(
+                                druid::commands::SHOW_SAVE_PANEL,
+                                export_dialog_options,
+                                Target::Auto,
+                            ),
+                        ))
+                        .append(MenuItem::new(
+                            LocalizedString::new("export-booklet")
+                                .with_placeholder("Export Booklet"),
+                            Command::new(EXPORT_BOOKLET, (), Target::Auto),
+                        )),
+                )
+                .append(MenuItem::new(
+                    LocalizedString::new("load-session").with_placeholder("Load Session"),
+                    Command::new(
+                        druid::commands::SHOW_OPEN_PANEL,
+                        open_session_options,
+                        Target::Auto,
+                    ),
+                ))
+                .append(MenuItem::new(
+                    LocalizedString::new("save-session").with_placeholder("Save Session"),
+                    Command::new(
+                        druid::commands::SHOW_SAVE_PANEL,
+                        save_session_options,
+                        Target::Auto,
+                    ),
+                )),
+        ),
+    );
+
+    let sink = launcher.get_external_handle();
+
+    launcher
+        .use_simple_logger()
+        .delegate(Delegate {
+            sink,
+            booklet: false,
+        })
+        .launch(app_state)
+        .expect("launch failed");
+}
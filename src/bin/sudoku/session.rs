@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{AppState, CellEdit};
+use druid_play::sudoku::geometry::{Board, Geometry, SIZES};
+use druid_play::sudoku::rules::Variant;
+use druid_play::sudoku::techniques::Difficulty;
+
+const SESSION_VERSION: u32 = 1;
+
+pub enum SessionError {
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    BadBoard,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Json(e) => write!(f, "Not a valid session file: {}", e),
+            SessionError::UnsupportedVersion(version) => write!(
+                f,
+                "Session file version {} is newer than this program supports ({})",
+                version, SESSION_VERSION
+            ),
+            SessionError::BadBoard => write!(f, "Session file doesn't describe a supported board"),
+        }
+    }
+}
+
+// Everything needed to pick a game back up, stored as JSON. `version` is bumped whenever
+// the layout changes in a way older readers can't ignore.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    version: u32,
+    geometry: Geometry,
+    variant: Variant,
+    values: Vec<u8>,
+    given: Vec<bool>,
+    from_solver: Vec<bool>,
+    notes: Vec<u16>,
+    eliminated: Vec<u16>,
+    notes_mode: bool,
+    undo_stack: Vec<Vec<CellEdit>>,
+    redo_stack: Vec<Vec<CellEdit>>,
+    elapsed: f64,
+    difficulty: Option<Difficulty>,
+    solution: Option<Vec<u8>>,
+    mistakes: u32,
+    check_mode: bool,
+    daily: Option<u64>,
+    missed: Vec<bool>,
+}
+
+impl Session {
+    pub fn capture(data: &AppState) -> Session {
+        Session {
+            version: SESSION_VERSION,
+            geometry: data.geometry(),
+            variant: data.layout.variant.clone(),
+            values: data.values.to_vec(),
+            given: data.given.to_vec(),
+            from_solver: data.from_solver.to_vec(),
+            notes: data.notes.to_vec(),
+            eliminated: data.eliminated.to_vec(),
+            notes_mode: data.notes_mode,
+            undo_stack: data.undo_stack.as_ref().clone(),
+            redo_stack: data.redo_stack.as_ref().clone(),
+            elapsed: data.elapsed,
+            difficulty: data.difficulty,
+            solution: data
+                .solution
+                .as_ref()
+                .map(|solution| solution.as_ref().clone()),
+            mistakes: data.mistakes,
+            check_mode: data.check_mode,
+            daily: data.daily,
+            missed: data.missed.to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<Session, SessionError> {
+        let session: Session = serde_json::from_str(text).map_err(SessionError::Json)?;
+        if session.version > SESSION_VERSION {
+            return Err(SessionError::UnsupportedVersion(session.version));
+        }
+        let size = session.geometry.size();
+        let cells = session.geometry.cells();
+        // Undo puts these back on the board, so they need the same checks as the board itself.
+        let fits = |value: u8, notes: u16| value as usize <= size && (notes as u32) >> size == 0;
+        let edits_fit = session
+            .undo_stack
+            .iter()
+            .chain(session.redo_stack.iter())
+            .flatten()
+            .all(|edit| {
+                edit.index < cells
+                    && fits(edit.before.value, edit.before.notes)
+                    && fits(edit.after.value, edit.after.notes)
+            });
+        let solution_fits = match &session.solution {
+            Some(solution) => {
+                solution.len() == cells
+                    && solution
+                        .iter()
+                        .all(|&value| value >= 1 && value as usize <= size)
+            }
+            Option::None => true,
+        };
+        if !SIZES.contains(&session.geometry)
+            || !session.variant.fits(session.geometry)
+            || session.values.len() != cells
+            || session.given.len() != cells
+            || session.from_solver.len() != cells
+            || session.notes.len() != cells
+            || session.eliminated.len() != cells
+            || session.values.iter().any(|&value| value as usize > size)
+            || session.notes.iter().any(|&notes| !fits(0, notes))
+            || !edits_fit
+            || !solution_fits
+            || session.missed.len() != cells
+        {
+            return Err(SessionError::BadBoard);
+        }
+        Ok(session)
+    }
+
+    pub fn restore(self, data: &mut AppState) {
+        data.load_board(&Board {
+            geometry: self.geometry,
+            variant: self.variant,
+            values: self.values,
+        });
+        data.given = Arc::new(self.given);
+        data.from_solver = Arc::new(self.from_solver);
+        data.notes = Arc::new(self.notes);
+        data.eliminated = Arc::new(self.eliminated);
+        data.notes_mode = self.notes_mode;
+        data.undo_stack = Arc::new(self.undo_stack);
+        data.redo_stack = Arc::new(self.redo_stack);
+        data.elapsed = self.elapsed;
+        data.difficulty = self.difficulty;
+        data.solution = self.solution.map(Arc::new);
+        data.mistakes = self.mistakes;
+        data.check_mode = self.check_mode;
+        data.daily = self.daily;
+        data.missed = Arc::new(self.missed);
+        data.finished = data.is_solved();
+    }
+}
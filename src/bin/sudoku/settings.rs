@@ -0,0 +1,47 @@
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use crate::keys::Edge;
+use crate::stats::home_path;
+
+const SETTINGS_FILE: &str = ".sudoku_settings.json";
+
+// Which cells light up around the focused one, whether exported boards show notes, and where
+// the arrow keys go at the edge of the board.
+#[derive(Clone, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub highlight_units: bool,
+    pub highlight_digits: bool,
+    pub highlight_notes: bool,
+    pub export_notes: bool,
+    pub edge: Edge,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            highlight_units: true,
+            highlight_digits: true,
+            highlight_notes: true,
+            export_notes: false,
+            edge: Edge::Stop,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        std::fs::read_to_string(home_path(SETTINGS_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(
+            home_path(SETTINGS_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+}
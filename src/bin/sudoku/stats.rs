@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use druid_play::sudoku::geometry::Geometry;
+use druid_play::sudoku::techniques::Difficulty;
+
+const STATS_FILE: &str = ".sudoku_stats.json";
+
+// Personal records, kept in the home directory. Entries are keyed by board and difficulty,
+// e.g. "9x9 Hard", with times in seconds.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub best_times: BTreeMap<String, f64>,
+    pub solved: BTreeMap<String, u32>,
+}
+
+impl Stats {
+    // A missing or unreadable file just means no records yet.
+    pub fn load() -> Stats {
+        std::fs::read_to_string(home_path(STATS_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(
+            home_path(STATS_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+
+    // Returns whether the time is a new best.
+    pub fn record(&mut self, geometry: Geometry, difficulty: Difficulty, seconds: f64) -> bool {
+        let key = key(geometry, difficulty);
+        *self.solved.entry(key.clone()).or_insert(0) += 1;
+        let best = match self.best_times.get(&key) {
+            Some(&best) => seconds < best,
+            Option::None => true,
+        };
+        if best {
+            self.best_times.insert(key, seconds);
+        }
+        best
+    }
+}
+
+pub fn key(geometry: Geometry, difficulty: Difficulty) -> String {
+    format!("{} {}", geometry.name(), difficulty.name())
+}
+
+pub fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// Falls back to the working directory when there's no home directory.
+pub fn home_path(file: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match home {
+        Some(home) => PathBuf::from(home).join(file),
+        Option::None => PathBuf::from(file),
+    }
+}
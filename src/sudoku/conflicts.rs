@@ -0,0 +1,139 @@
+use crate::sudoku::geometry::Layout;
+use crate::sudoku::solver;
+use crate::sudoku::techniques::{cell_name, list};
+
+// Why cells are shown as illegal: two of them repeat a digit where the rules forbid it, or a
+// killer cage's digits add up wrong.
+pub enum Conflict {
+    Repeat {
+        cells: (usize, usize),
+        value: u8,
+        // Units holding both cells, or failing that the rules that make them peers.
+        reasons: Vec<String>,
+    },
+    CageSum {
+        cells: Vec<usize>,
+        total: u32,
+        sum: u32,
+    },
+}
+
+impl Conflict {
+    pub fn cells(&self) -> Vec<usize> {
+        match self {
+            Conflict::Repeat { cells, .. } => vec![cells.0, cells.1],
+            Conflict::CageSum { cells, .. } => cells.clone(),
+        }
+    }
+
+    pub fn involves(&self, index: usize) -> bool {
+        self.cells().contains(&index)
+    }
+
+    pub fn explain(&self, layout: &Layout) -> String {
+        let geometry = layout.geometry;
+        match self {
+            Conflict::Repeat {
+                cells,
+                value,
+                reasons,
+            } => format!(
+                "{} and {} both hold {} in {}",
+                cell_name(geometry, cells.0),
+                cell_name(geometry, cells.1),
+                geometry.symbol(*value),
+                list(reasons.iter().cloned())
+            ),
+            Conflict::CageSum { cells, total, sum } => format!(
+                "the cage at {} adds up to {}, but its sum is {}",
+                cell_name(geometry, cells[0]),
+                total,
+                sum
+            ),
+        }
+    }
+}
+
+// Every conflict on the board; each illegal cell is part of at least one.
+pub fn conflicts(layout: &Layout, board: &[u8]) -> Vec<Conflict> {
+    let geometry = layout.geometry;
+    let mut conflicts = Vec::new();
+    for (index, &value) in board.iter().enumerate() {
+        if value == 0 {
+            continue;
+        }
+        for &peer in layout.peers[index].iter() {
+            if peer < index || board[peer] != value {
+                continue;
+            }
+            let mut reasons: Vec<String> = layout
+                .units
+                .iter()
+                .zip(layout.unit_names.iter())
+                .filter(|(unit, _)| unit.contains(&index) && unit.contains(&peer))
+                .map(|(_, name)| name.clone())
+                .collect();
+            if reasons.is_empty() {
+                reasons = layout
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.peers(geometry, index).contains(&peer))
+                    .map(|rule| format!("breach of the {} rule", rule.name()))
+                    .collect();
+            }
+            conflicts.push(Conflict::Repeat {
+                cells: (index, peer),
+                value,
+                reasons,
+            });
+        }
+    }
+    for cage in layout.variant.cages.iter() {
+        let total: u32 = cage.cells.iter().map(|&index| board[index] as u32).sum();
+        let complete = cage.cells.iter().all(|&index| board[index] != 0);
+        if total > cage.sum || complete && total != cage.sum {
+            let mut cells = cage.cells.clone();
+            cells.sort_unstable();
+            conflicts.push(Conflict::CageSum {
+                cells,
+                total,
+                sum: cage.sum,
+            });
+        }
+    }
+    conflicts
+}
+
+// For a board that breaks no rule but still can't be solved: player entries (cells that aren't
+// given) whose removal lets it be solved again. Entries that disagree with a solution of the
+// givens are taken out, then put back one at a time wherever the board stays solvable, so every
+// one left is needed. With a unique solution that's also the smallest such set. Returns None when
+// the givens themselves have no solution.
+pub fn entries_to_remove(layout: &Layout, board: &[u8], given: &[bool]) -> Option<Vec<usize>> {
+    if !solver::find_solutions(layout, board, 1).is_empty() {
+        return Some(Vec::new());
+    }
+    let givens: Vec<u8> = board
+        .iter()
+        .zip(given.iter())
+        .map(|(&value, &given)| if given { value } else { 0 })
+        .collect();
+    let solution = solver::find_solutions(layout, &givens, 1).pop()?;
+    let mut removed: Vec<usize> = (0..board.len())
+        .filter(|&index| !given[index] && board[index] != 0 && board[index] != solution[index])
+        .collect();
+    let mut trial = board.to_vec();
+    for &index in removed.iter() {
+        trial[index] = 0;
+    }
+    removed.retain(|&index| {
+        trial[index] = board[index];
+        if solver::find_solutions(layout, &trial, 1).is_empty() {
+            trial[index] = 0;
+            true
+        } else {
+            false
+        }
+    });
+    Some(removed)
+}
@@ -0,0 +1,469 @@
+use std::fmt;
+
+use crate::sudoku::geometry::{Board, Geometry};
+use crate::sudoku::rules::{Cage, Variant};
+
+pub enum FormatError {
+    NoPuzzle,
+    BadCharacter {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    BadRowLength {
+        line: usize,
+        found: usize,
+    },
+    IncompleteGrid {
+        rows: usize,
+        size: usize,
+    },
+    BadRule {
+        line: usize,
+    },
+    BadVariant {
+        puzzle: usize,
+    },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::NoPuzzle => write!(f, "The file doesn't contain a puzzle"),
+            FormatError::BadCharacter {
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "Line {}, column {}: '{}' is not a symbol for this board size or a blank ('.', '0' or '-')",
+                line, column, character
+            ),
+            FormatError::BadRowLength { line, found } => write!(
+                f,
+                "Line {}: {} cells is neither a grid row nor a whole puzzle of a 4x4, 6x6, 9x9, 12x12 or 16x16 board",
+                line, found
+            ),
+            FormatError::IncompleteGrid { rows, size } => {
+                write!(f, "The last grid has {} rows instead of {}", rows, size)
+            }
+            FormatError::BadRule { line } => write!(
+                f,
+                "Line {}: expected 'diagonals', 'anti-knight', 'anti-king', 'cage <sum> <cells>' or 'regions' after a puzzle",
+                line
+            ),
+            FormatError::BadVariant { puzzle } => write!(
+                f,
+                "Puzzle {}: the regions or cages don't fit the board",
+                puzzle
+            ),
+        }
+    }
+}
+
+// Reads any of the supported layouts: one puzzle per line, grids of one row per line with optional
+// '|' and '-+-' separators, and SadMan Sudoku (.sdk) files with '#' metadata and [Puzzle] sections.
+// The board size comes from the number of cells: 81 on a line or 9 per grid row is 9x9, and so on.
+// A [Rules] section after a puzzle turns it into a variant.
+pub fn parse(text: &str) -> Result<Vec<Board>, FormatError> {
+    let puzzles = parse_each(text);
+    if puzzles.is_empty() {
+        return Err(FormatError::NoPuzzle);
+    }
+    puzzles.into_iter().collect()
+}
+
+// Like `parse`, but carries on past a puzzle that can't be read, giving its error in its place so
+// the rest of a batch still gets through.
+pub fn parse_each(text: &str) -> Vec<Result<Board, FormatError>> {
+    let mut reader = Reader {
+        puzzles: Vec::new(),
+        grid: Vec::new(),
+        grid_geometry: Geometry::CLASSIC,
+        grid_error: Option::None,
+        in_puzzle_section: true,
+        in_rules_section: false,
+        region_rows: Option::None,
+    };
+    for (number, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+        reader.read_line(number + 1, line.trim());
+    }
+    reader.finish()
+}
+
+struct Reader {
+    puzzles: Vec<Result<Board, FormatError>>,
+    grid: Vec<Cell>,
+    grid_geometry: Geometry,
+    // The first bad row of the grid being read. The grid's remaining rows are still read, so the
+    // puzzles after it stay in step, and the error takes its place once it's complete.
+    grid_error: Option<FormatError>,
+    in_puzzle_section: bool,
+    in_rules_section: bool,
+    region_rows: Option<Vec<Cell>>,
+}
+
+impl Reader {
+    fn read_line(&mut self, number: usize, line: &str) {
+        if line.starts_with('[') {
+            self.in_puzzle_section = line.eq_ignore_ascii_case("[puzzle]");
+            self.in_rules_section = line.eq_ignore_ascii_case("[rules]");
+            return;
+        }
+        if self.in_rules_section && !line.is_empty() && !line.starts_with('#') {
+            if let Err(e) = self.read_rule(number, line) {
+                self.region_rows = Option::None;
+                match self.puzzles.last_mut() {
+                    Some(puzzle) => *puzzle = Err(e),
+                    Option::None => self.puzzles.push(Err(e)),
+                }
+            }
+            return;
+        }
+        let row_size = if self.grid.is_empty() {
+            Option::None
+        } else {
+            Some(self.grid_geometry.size())
+        };
+        if !self.in_puzzle_section
+            || line.is_empty()
+            || line.starts_with('#')
+            || is_separator(line, row_size)
+        {
+            return;
+        }
+        if self.grid.is_empty() {
+            if let Err(e) = self.start_puzzle(number, line) {
+                self.puzzles.push(Err(e));
+            }
+            return;
+        }
+        let size = self.grid_geometry.size();
+        let row = split_cells(line, number).and_then(|cells| {
+            if cells.len() == size {
+                Ok(cells)
+            } else {
+                Err(FormatError::BadRowLength {
+                    line: number,
+                    found: cells.len(),
+                })
+            }
+        });
+        match row {
+            Ok(cells) => self.grid.extend(cells),
+            Err(e) => {
+                self.grid_error.get_or_insert(e);
+                self.grid.extend((0..size).map(|column| Cell {
+                    line: number,
+                    column: column + 1,
+                    character: '.',
+                }));
+            }
+        }
+        if self.grid.len() == self.grid_geometry.cells() {
+            let puzzle = match self.grid_error.take() {
+                Some(e) => Err(e),
+                Option::None => to_board(self.grid_geometry, &self.grid),
+            };
+            self.puzzles.push(puzzle);
+            self.grid.clear();
+        }
+    }
+
+    // A line with no grid in progress is a whole puzzle, or the first row of a grid.
+    fn start_puzzle(&mut self, number: usize, line: &str) -> Result<(), FormatError> {
+        let first_token = line.split_whitespace().next().unwrap_or("");
+        if let Some(geometry) = whole_puzzle(first_token.chars().count(), first_token) {
+            let board = to_board(geometry, &split_cells(first_token, number)?)?;
+            self.puzzles.push(Ok(board));
+            return Ok(());
+        }
+        let cells = split_cells(line, number)?;
+        if let Some(geometry) = whole_puzzle(cells.len(), line) {
+            let board = to_board(geometry, &cells)?;
+            self.puzzles.push(Ok(board));
+            return Ok(());
+        }
+        self.grid_geometry = match Geometry::for_size(cells.len()) {
+            Some(geometry) => geometry,
+            None => {
+                return Err(FormatError::BadRowLength {
+                    line: number,
+                    found: cells.len(),
+                })
+            }
+        };
+        self.grid.extend(cells);
+        Ok(())
+    }
+
+    fn read_rule(&mut self, number: usize, line: &str) -> Result<(), FormatError> {
+        let puzzle = match self.puzzles.last_mut() {
+            Some(Ok(puzzle)) => puzzle,
+            // Its puzzle couldn't be read, and has already been reported.
+            Some(Err(_)) => return Ok(()),
+            Option::None => return Err(FormatError::BadRule { line: number }),
+        };
+        match self.region_rows.as_mut() {
+            Some(rows) => {
+                rows.extend(split_cells(line, number)?);
+                if rows.len() >= puzzle.geometry.cells() {
+                    puzzle.variant.regions = Some(to_regions(puzzle.geometry, rows)?);
+                    self.region_rows = Option::None;
+                }
+            }
+            Option::None if line.eq_ignore_ascii_case("regions") => {
+                self.region_rows = Some(Vec::new());
+            }
+            Option::None => parse_rule(puzzle, line, number)?,
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<Result<Board, FormatError>> {
+        if self.region_rows.is_some() {
+            let puzzle = self.puzzles.len();
+            if let Some(last) = self.puzzles.last_mut() {
+                *last = Err(FormatError::BadVariant { puzzle });
+            }
+        }
+        if !self.grid.is_empty() {
+            self.puzzles.push(Err(FormatError::IncompleteGrid {
+                rows: self.grid.len() / self.grid_geometry.size(),
+                size: self.grid_geometry.size(),
+            }));
+        }
+        for (index, puzzle) in self.puzzles.iter_mut().enumerate() {
+            if let Ok(board) = puzzle {
+                if !board.variant.fits(board.geometry) {
+                    *puzzle = Err(FormatError::BadVariant { puzzle: index + 1 });
+                }
+            }
+        }
+        self.puzzles
+    }
+}
+
+pub fn to_line(board: &Board) -> String {
+    let blank = if board.geometry.size() == 16 {
+        '.'
+    } else {
+        '0'
+    };
+    board
+        .values
+        .iter()
+        .map(|&value| {
+            if value == 0 {
+                blank
+            } else {
+                board.geometry.symbol(value)
+            }
+        })
+        .collect()
+}
+
+// Variants get [Puzzle] and [Rules] sections so the rules survive a round trip.
+pub fn to_sdk(board: &Board) -> String {
+    let mut result = String::new();
+    let variant = &board.variant;
+    if !variant.is_classic() {
+        result.push_str("[Puzzle]\n");
+    }
+    for row in board.values.chunks(board.geometry.size()) {
+        for &value in row {
+            result.push(if value == 0 {
+                '.'
+            } else {
+                board.geometry.symbol(value)
+            });
+        }
+        result.push('\n');
+    }
+    if !variant.is_classic() {
+        result.push_str("[Rules]\n");
+        if variant.diagonals {
+            result.push_str("diagonals\n");
+        }
+        if variant.anti_knight {
+            result.push_str("anti-knight\n");
+        }
+        if variant.anti_king {
+            result.push_str("anti-king\n");
+        }
+        for cage in variant.cages.iter() {
+            result.push_str(&format!("cage {}", cage.sum));
+            for &index in cage.cells.iter() {
+                result.push_str(&format!(
+                    " r{}c{}",
+                    board.geometry.row_of(index) + 1,
+                    board.geometry.col_of(index) + 1
+                ));
+            }
+            result.push('\n');
+        }
+        if let Some(regions) = &variant.regions {
+            result.push_str("regions\n");
+            for row in regions.chunks(board.geometry.size()) {
+                for &region in row {
+                    result.push(board.geometry.symbol(region as u8 + 1));
+                }
+                result.push('\n');
+            }
+        }
+    }
+    result
+}
+
+// A cell as written in the file, with its 1-based line and column for error messages.
+struct Cell {
+    line: usize,
+    column: usize,
+    character: char,
+}
+
+// '-' is a blank as well as a rule between boxes, so a line of dashes is only a separator when it
+// has a '+' or '=' in it, or couldn't be a row (or whole puzzle, at the start) of the grid.
+fn is_separator(line: &str, row_size: Option<usize>) -> bool {
+    if !line
+        .chars()
+        .all(|c| matches!(c, '-' | '+' | '=' | '|' | ' '))
+    {
+        return false;
+    }
+    if line.contains('+') || line.contains('=') {
+        return true;
+    }
+    let dashes = line.chars().filter(|&c| c == '-').count();
+    match row_size {
+        Some(size) => dashes != size,
+        Option::None => {
+            Geometry::for_size(dashes).is_none() && Geometry::for_cells(dashes).is_none()
+        }
+    }
+}
+
+// Sixteen cells could be a whole 4x4 puzzle or one row of a 16x16 grid; it's only read as a
+// 4x4 puzzle when every cell is valid there.
+fn whole_puzzle(cells: usize, text: &str) -> Option<Geometry> {
+    let geometry = Geometry::for_cells(cells)?;
+    if geometry.size() == 4
+        && !text
+            .chars()
+            .filter(|&c| !matches!(c, ' ' | '\t' | '|'))
+            .all(|c| geometry.is_blank(c) || geometry.value_of(c).is_some())
+    {
+        return None;
+    }
+    Some(geometry)
+}
+
+fn split_cells(text: &str, line: usize) -> Result<Vec<Cell>, FormatError> {
+    let mut cells = Vec::new();
+    for (column, character) in text.chars().enumerate() {
+        match character {
+            '0'..='9' | 'A'..='Z' | 'a'..='z' | '.' | '-' => cells.push(Cell {
+                line,
+                column: column + 1,
+                character,
+            }),
+            ' ' | '\t' | '|' => {}
+            _ => {
+                return Err(FormatError::BadCharacter {
+                    line,
+                    column: column + 1,
+                    character,
+                })
+            }
+        }
+    }
+    Ok(cells)
+}
+
+fn parse_rule(puzzle: &mut Board, line: &str, number: usize) -> Result<(), FormatError> {
+    let mut words = line.split_whitespace();
+    match words
+        .next()
+        .map(|word| word.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("diagonals") | Some("x-sudoku") => puzzle.variant.diagonals = true,
+        Some("anti-knight") => puzzle.variant.anti_knight = true,
+        Some("anti-king") => puzzle.variant.anti_king = true,
+        Some("cage") => {
+            let sum = words
+                .next()
+                .and_then(|word| word.parse().ok())
+                .ok_or(FormatError::BadRule { line: number })?;
+            let mut cells = Vec::new();
+            for word in words {
+                cells.push(
+                    parse_cell_name(puzzle.geometry, word)
+                        .ok_or(FormatError::BadRule { line: number })?,
+                );
+            }
+            if cells.is_empty() {
+                return Err(FormatError::BadRule { line: number });
+            }
+            puzzle.variant.cages.push(Cage { cells, sum });
+        }
+        _ => return Err(FormatError::BadRule { line: number }),
+    }
+    Ok(())
+}
+
+// Cells are named as in hints, "r4c7" for row 4, column 7.
+fn parse_cell_name(geometry: Geometry, name: &str) -> Option<usize> {
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_prefix('r')?;
+    let split = name.find('c')?;
+    let row: usize = name[..split].parse().ok()?;
+    let col: usize = name[split + 1..].parse().ok()?;
+    if row < 1 || row > geometry.size() || col < 1 || col > geometry.size() {
+        return Option::None;
+    }
+    Some((row - 1) * geometry.size() + col - 1)
+}
+
+// Regions are drawn as a grid using the board's own symbols, one per region.
+fn to_regions(geometry: Geometry, cells: &[Cell]) -> Result<Vec<usize>, FormatError> {
+    let mut regions = Vec::with_capacity(cells.len());
+    for cell in cells {
+        match geometry.value_of(cell.character) {
+            Some(value) if regions.len() < geometry.cells() => regions.push(value as usize - 1),
+            _ => {
+                return Err(FormatError::BadCharacter {
+                    line: cell.line,
+                    column: cell.column,
+                    character: cell.character,
+                })
+            }
+        }
+    }
+    Ok(regions)
+}
+
+fn to_board(geometry: Geometry, cells: &[Cell]) -> Result<Board, FormatError> {
+    let mut values = Vec::with_capacity(cells.len());
+    for cell in cells {
+        if geometry.is_blank(cell.character) {
+            values.push(0);
+        } else {
+            match geometry.value_of(cell.character) {
+                Some(value) => values.push(value),
+                None => {
+                    return Err(FormatError::BadCharacter {
+                        line: cell.line,
+                        column: cell.column,
+                        character: cell.character,
+                    })
+                }
+            }
+        }
+    }
+    Ok(Board {
+        geometry,
+        variant: Variant::default(),
+        values,
+    })
+}
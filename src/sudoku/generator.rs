@@ -0,0 +1,144 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::sudoku::geometry::{Board, Geometry, Layout};
+use crate::sudoku::rules::Variant;
+use crate::sudoku::solver::{self, Observer};
+use crate::sudoku::techniques::{self, Difficulty};
+
+const MAX_ATTEMPTS: usize = 50;
+
+pub struct Puzzle {
+    pub board: Board,
+    pub solution: Vec<u8>,
+    pub difficulty: Difficulty,
+}
+
+// None when the rules leave no way to fill the grid at all, or once `cancel` is set; some rule
+// combinations take a long search to rule out.
+pub fn generate(
+    geometry: Geometry,
+    variant: &Variant,
+    difficulty: Difficulty,
+    cancel: &AtomicBool,
+) -> Option<Puzzle> {
+    generate_with(
+        geometry,
+        variant,
+        difficulty,
+        &mut rand::thread_rng(),
+        cancel,
+    )
+}
+
+// The same seed always gives the same puzzle on any machine. ChaCha8's output is fixed by its
+// specification, unlike StdRng's, and rand is pinned in Cargo.toml because its shuffling and
+// ranges turn that output into the puzzle.
+pub fn generate_seeded(
+    geometry: Geometry,
+    variant: &Variant,
+    difficulty: Difficulty,
+    seed: u64,
+) -> Option<Puzzle> {
+    generate_with(
+        geometry,
+        variant,
+        difficulty,
+        &mut ChaCha8Rng::seed_from_u64(seed),
+        &AtomicBool::new(false),
+    )
+}
+
+// Keeps generating until a puzzle grades exactly at the requested level, falling back to the
+// hardest one seen below it. Killer cages are left out, since their sums come from the solution.
+fn generate_with(
+    geometry: Geometry,
+    variant: &Variant,
+    difficulty: Difficulty,
+    rng: &mut impl Rng,
+    cancel: &AtomicBool,
+) -> Option<Puzzle> {
+    let variant = Variant {
+        cages: Vec::new(),
+        ..variant.clone()
+    };
+    let layout = Layout::new(geometry, &variant);
+    let mut best: Option<Puzzle> = None;
+    for _ in 0..MAX_ATTEMPTS {
+        let solution = random_grid(&layout, rng, cancel)?;
+        let values = remove_clues(&layout, solution.clone(), difficulty, rng);
+        let grade = techniques::grade(&layout, &values);
+        let board = Board {
+            geometry,
+            variant: variant.clone(),
+            values,
+        };
+        if grade == difficulty {
+            return Some(Puzzle {
+                board,
+                solution,
+                difficulty: grade,
+            });
+        }
+        let better = match &best {
+            Some(puzzle) => grade > puzzle.difficulty,
+            None => true,
+        };
+        if better {
+            best = Some(Puzzle {
+                board,
+                solution,
+                difficulty: grade,
+            });
+        }
+    }
+    best
+}
+
+// Tries the candidates for each guess in a random order, so the first solution found is a
+// random one.
+struct Shuffle<'r, R> {
+    rng: &'r mut R,
+    cancel: &'r AtomicBool,
+}
+
+impl<'r, R: Rng> Observer for Shuffle<'r, R> {
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn order(&mut self, digits: &mut [u8]) {
+        digits.shuffle(self.rng);
+    }
+}
+
+// Solves the empty board with the propagating solver, so rules that constrain the grid heavily
+// are found out quickly. None if no grid satisfies them.
+fn random_grid(layout: &Layout, rng: &mut impl Rng, cancel: &AtomicBool) -> Option<Vec<u8>> {
+    let empty = vec![0; layout.geometry.cells()];
+    solver::observed_solutions(layout, &empty, 1, &mut Shuffle { rng, cancel })?.pop()
+}
+
+// Clears cells in random order, putting back any whose removal breaks uniqueness or needs
+// harder techniques than requested.
+fn remove_clues(
+    layout: &Layout,
+    mut board: Vec<u8>,
+    difficulty: Difficulty,
+    rng: &mut impl Rng,
+) -> Vec<u8> {
+    let mut order: Vec<usize> = (0..board.len()).collect();
+    order.shuffle(rng);
+    for index in order {
+        let value = board[index];
+        board[index] = 0;
+        if solver::find_solutions(layout, &board, 2).len() != 1
+            || techniques::grade(layout, &board) > difficulty
+        {
+            board[index] = value;
+        }
+    }
+    board
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+
+use std::ops::RangeInclusive;
+
+use crate::sudoku::rules::{Rule, Variant};
+
+const DIGITS: &[u8] = b"123456789ABC";
+const HEX_DIGITS: &[u8] = b"0123456789ABCDEF";
+
+// The shape of a box; the board is box_rows * box_cols cells on a side, so 2x3 boxes make a 6x6 board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Geometry {
+    pub box_rows: usize,
+    pub box_cols: usize,
+}
+
+pub const SIZES: [Geometry; 5] = [
+    Geometry {
+        box_rows: 2,
+        box_cols: 2,
+    },
+    Geometry {
+        box_rows: 2,
+        box_cols: 3,
+    },
+    Geometry::CLASSIC,
+    Geometry {
+        box_rows: 3,
+        box_cols: 4,
+    },
+    Geometry {
+        box_rows: 4,
+        box_cols: 4,
+    },
+];
+
+impl Geometry {
+    pub const CLASSIC: Geometry = Geometry {
+        box_rows: 3,
+        box_cols: 3,
+    };
+
+    pub fn for_size(size: usize) -> Option<Geometry> {
+        SIZES
+            .iter()
+            .copied()
+            .find(|geometry| geometry.size() == size)
+    }
+
+    pub fn for_cells(cells: usize) -> Option<Geometry> {
+        SIZES
+            .iter()
+            .copied()
+            .find(|geometry| geometry.cells() == cells)
+    }
+
+    pub fn name(self) -> String {
+        format!("{}x{}", self.size(), self.size())
+    }
+
+    pub fn size(self) -> usize {
+        self.box_rows * self.box_cols
+    }
+
+    pub fn cells(self) -> usize {
+        self.size() * self.size()
+    }
+
+    pub fn digits(self) -> RangeInclusive<u8> {
+        1..=self.size() as u8
+    }
+
+    pub fn all_candidates(self) -> u16 {
+        ((1u32 << self.size()) - 1) as u16
+    }
+
+    pub fn row_of(self, index: usize) -> usize {
+        index / self.size()
+    }
+
+    pub fn col_of(self, index: usize) -> usize {
+        index % self.size()
+    }
+
+    // Boxes are numbered left to right, top to bottom; each band holds box_rows of them.
+    pub fn box_of(self, index: usize) -> usize {
+        (self.row_of(index) / self.box_rows) * self.box_rows + self.col_of(index) / self.box_cols
+    }
+
+    // 16x16 boards use hexadecimal 0-F; smaller ones count 1-9 and carry on with letters.
+    pub fn symbol(self, value: u8) -> char {
+        if self.size() == 16 {
+            HEX_DIGITS[value as usize - 1] as char
+        } else {
+            DIGITS[value as usize - 1] as char
+        }
+    }
+
+    pub fn value_of(self, symbol: char) -> Option<u8> {
+        let symbol = symbol.to_ascii_uppercase();
+        self.digits().find(|&value| self.symbol(value) == symbol)
+    }
+
+    // '0' is a symbol on 16x16 boards, so only '.' and '-' mark a blank there.
+    pub fn is_blank(self, symbol: char) -> bool {
+        symbol == '.' || symbol == '-' || symbol == '0' && self.size() != 16
+    }
+}
+
+// Units are the rows, then the columns, then the boxes (or jigsaw regions), then any the rules
+// add; peers are the cells that may not share a value, whether through a unit or a rule.
+pub struct Layout {
+    pub geometry: Geometry,
+    pub variant: Variant,
+    pub rules: Vec<Box<dyn Rule>>,
+    pub units: Vec<Vec<usize>>,
+    pub unit_names: Vec<String>,
+    pub regions: Vec<usize>,
+    pub peers: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    pub fn new(geometry: Geometry, variant: &Variant) -> Layout {
+        let size = geometry.size();
+        let rules = variant.rules();
+        let mut units: Vec<Vec<usize>> = Vec::new();
+        let mut unit_names = Vec::new();
+        for row in 0..size {
+            units.push((0..size).map(|col| row * size + col).collect());
+            unit_names.push(format!("row {}", row + 1));
+        }
+        for col in 0..size {
+            units.push((0..size).map(|row| row * size + col).collect());
+            unit_names.push(format!("column {}", col + 1));
+        }
+        let regions = match rules.iter().find_map(|rule| rule.regions()) {
+            Some(regions) => {
+                for region in 0..size {
+                    units.push(
+                        (0..geometry.cells())
+                            .filter(|&index| regions[index] == region)
+                            .collect(),
+                    );
+                    unit_names.push(format!("region {}", region + 1));
+                }
+                regions
+            }
+            Option::None => {
+                for grid in 0..size {
+                    let top = (grid / geometry.box_rows) * geometry.box_rows;
+                    let left = (grid % geometry.box_rows) * geometry.box_cols;
+                    units.push(
+                        (0..size)
+                            .map(|other| {
+                                (top + other / geometry.box_cols) * size
+                                    + left
+                                    + other % geometry.box_cols
+                            })
+                            .collect(),
+                    );
+                    unit_names.push(format!("box {}", grid + 1));
+                }
+                (0..geometry.cells())
+                    .map(|index| geometry.box_of(index))
+                    .collect()
+            }
+        };
+        for rule in rules.iter() {
+            for (name, unit) in rule.units(geometry) {
+                units.push(unit);
+                unit_names.push(name);
+            }
+        }
+        let mut peers = vec![Vec::new(); geometry.cells()];
+        for unit in units.iter() {
+            for &index in unit {
+                for &other in unit {
+                    if other != index && !peers[index].contains(&other) {
+                        peers[index].push(other);
+                    }
+                }
+            }
+        }
+        for (index, cell_peers) in peers.iter_mut().enumerate() {
+            for rule in rules.iter() {
+                for other in rule.peers(geometry, index) {
+                    if other != index && !cell_peers.contains(&other) {
+                        cell_peers.push(other);
+                    }
+                }
+            }
+        }
+        Layout {
+            geometry,
+            variant: variant.clone(),
+            rules,
+            units,
+            unit_names,
+            regions,
+            peers,
+        }
+    }
+
+    // A value is legal when no peer holds it and every rule accepts it.
+    pub fn is_legal_move(&self, board: &[u8], index: usize, value: u8) -> bool {
+        self.peers[index].iter().all(|&peer| board[peer] != value)
+            && self
+                .rules
+                .iter()
+                .all(|rule| rule.is_legal(board, index, value))
+    }
+
+    pub fn boxes(&self) -> &[Vec<usize>] {
+        let size = self.geometry.size();
+        &self.units[2 * size..3 * size]
+    }
+}
+
+#[derive(Clone)]
+pub struct Board {
+    pub geometry: Geometry,
+    pub variant: Variant,
+    pub values: Vec<u8>,
+}
+
+impl Board {
+    pub fn empty(geometry: Geometry) -> Board {
+        Board {
+            geometry,
+            variant: Variant::default(),
+            values: vec![0; geometry.cells()],
+        }
+    }
+}
@@ -0,0 +1,11 @@
+// The sudoku board, rules, solver, conflict analysis, file formats, image import and printing,
+// kept free of any UI so they can be used from the command line as well as the druid app.
+pub mod conflicts;
+pub mod formats;
+pub mod generator;
+pub mod geometry;
+pub mod print;
+pub mod recognize;
+pub mod rules;
+pub mod solver;
+pub mod techniques;
@@ -0,0 +1,568 @@
+use crate::sudoku::generator::Puzzle;
+use crate::sudoku::geometry::{Board, Layout};
+
+// Page sizes are in points, as in PDF; A4 for booklets.
+pub const A4: (f64, f64) = (595., 842.);
+const BOARD_PAGE: f64 = 480.;
+const MARGIN: f64 = 20.;
+// Boards across and down a booklet page.
+const PUZZLE_TILES: (usize, usize) = (2, 3);
+const SOLUTION_TILES: (usize, usize) = (3, 4);
+const CAPTION: f64 = 14.;
+
+// What a mark is drawn as. Every output format maps these to the same colours and weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ink {
+    Grid,
+    Given,
+    Entry,
+    Note,
+    Rule,
+    Caption,
+}
+
+impl Ink {
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Ink::Grid | Ink::Given | Ink::Caption => (0, 0, 0),
+            Ink::Entry => (0, 64, 160),
+            Ink::Note => (96, 96, 96),
+            Ink::Rule => (120, 120, 120),
+        }
+    }
+
+    pub fn is_bold(self) -> bool {
+        self == Ink::Given
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mark {
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        width: f64,
+        dashed: bool,
+        ink: Ink,
+    },
+    // Symbols are centred on `at`; captions start there.
+    Text {
+        at: (f64, f64),
+        size: f64,
+        text: String,
+        ink: Ink,
+    },
+}
+
+// One page of output, with y growing downwards.
+pub struct Page {
+    pub width: f64,
+    pub height: f64,
+    pub marks: Vec<Mark>,
+}
+
+// The current board on a page of its own. Notes are only drawn when given.
+pub fn board_page(board: &Board, given: &[bool], notes: Option<&[u16]>) -> Page {
+    let mut page = Page {
+        width: BOARD_PAGE,
+        height: BOARD_PAGE,
+        marks: Vec::new(),
+    };
+    draw_board(
+        &mut page.marks,
+        board,
+        given,
+        notes,
+        (MARGIN, MARGIN),
+        BOARD_PAGE - 2. * MARGIN,
+    );
+    page
+}
+
+// Puzzle pages with six boards each, followed by solution pages with twelve smaller ones.
+pub fn booklet(puzzles: &[Puzzle]) -> Vec<Page> {
+    let boards: Vec<(String, Board, Vec<bool>)> = puzzles
+        .iter()
+        .enumerate()
+        .map(|(number, puzzle)| {
+            (
+                format!("Puzzle {} · {}", number + 1, puzzle.difficulty.name()),
+                puzzle.board.clone(),
+                puzzle
+                    .board
+                    .values
+                    .iter()
+                    .map(|&value| value != 0)
+                    .collect(),
+            )
+        })
+        .collect();
+    let solutions: Vec<(String, Board, Vec<bool>)> = puzzles
+        .iter()
+        .enumerate()
+        .map(|(number, puzzle)| {
+            (
+                format!("Solution {}", number + 1),
+                Board {
+                    values: puzzle.solution.clone(),
+                    ..puzzle.board.clone()
+                },
+                puzzle
+                    .board
+                    .values
+                    .iter()
+                    .map(|&value| value != 0)
+                    .collect(),
+            )
+        })
+        .collect();
+    let mut pages = tile(&boards, PUZZLE_TILES);
+    pages.append(&mut tile(&solutions, SOLUTION_TILES));
+    pages
+}
+
+// Lays the boards out on A4 pages in a grid of tiles, with a caption above each board.
+fn tile(boards: &[(String, Board, Vec<bool>)], (across, down): (usize, usize)) -> Vec<Page> {
+    let tile_width = (A4.0 - 2. * MARGIN) / across as f64;
+    let tile_height = (A4.1 - 2. * MARGIN) / down as f64;
+    let side = (tile_width - MARGIN).min(tile_height - CAPTION - MARGIN);
+    boards
+        .chunks(across * down)
+        .map(|chunk| {
+            let mut page = Page {
+                width: A4.0,
+                height: A4.1,
+                marks: Vec::new(),
+            };
+            for (position, (title, board, given)) in chunk.iter().enumerate() {
+                let x = MARGIN + (position % across) as f64 * tile_width + (tile_width - side) / 2.;
+                let y = MARGIN + (position / across) as f64 * tile_height;
+                page.marks.push(Mark::Text {
+                    at: (x, y + CAPTION / 2.),
+                    size: CAPTION * 0.8,
+                    text: title.clone(),
+                    ink: Ink::Caption,
+                });
+                draw_board(&mut page.marks, board, given, None, (x, y + CAPTION), side);
+            }
+            page
+        })
+        .collect()
+}
+
+fn line(
+    marks: &mut Vec<Mark>,
+    from: (f64, f64),
+    to: (f64, f64),
+    width: f64,
+    dashed: bool,
+    ink: Ink,
+) {
+    marks.push(Mark::Line {
+        from,
+        to,
+        width,
+        dashed,
+        ink,
+    });
+}
+
+// Draws the board as a square of `side` points with its top left corner at `origin`.
+pub fn draw_board(
+    marks: &mut Vec<Mark>,
+    board: &Board,
+    given: &[bool],
+    notes: Option<&[u16]>,
+    origin: (f64, f64),
+    side: f64,
+) {
+    let layout = Layout::new(board.geometry, &board.variant);
+    let geometry = board.geometry;
+    let size = geometry.size();
+    let cell = side / size as f64;
+    let thin = (side / 600.).max(0.5);
+    let thick = thin * 4.;
+    let corner =
+        |row: usize, col: usize| (origin.0 + col as f64 * cell, origin.1 + row as f64 * cell);
+
+    // Each inner edge once, thick where it separates boxes or jigsaw regions, then the outline.
+    for index in 0..geometry.cells() {
+        let (row, col) = (geometry.row_of(index), geometry.col_of(index));
+        if col + 1 < size {
+            let width = if layout.regions[index] == layout.regions[index + 1] {
+                thin
+            } else {
+                thick
+            };
+            line(
+                marks,
+                corner(row, col + 1),
+                corner(row + 1, col + 1),
+                width,
+                false,
+                Ink::Grid,
+            );
+        }
+        if row + 1 < size {
+            let width = if layout.regions[index] == layout.regions[index + size] {
+                thin
+            } else {
+                thick
+            };
+            line(
+                marks,
+                corner(row + 1, col),
+                corner(row + 1, col + 1),
+                width,
+                false,
+                Ink::Grid,
+            );
+        }
+    }
+    for &(from, to) in [
+        ((0, 0), (0, size)),
+        ((0, size), (size, size)),
+        ((size, size), (size, 0)),
+        ((size, 0), (0, 0)),
+    ]
+    .iter()
+    {
+        line(
+            marks,
+            corner(from.0, from.1),
+            corner(to.0, to.1),
+            thick,
+            false,
+            Ink::Grid,
+        );
+    }
+    if board.variant.diagonals {
+        line(
+            marks,
+            corner(0, 0),
+            corner(size, size),
+            thin,
+            false,
+            Ink::Rule,
+        );
+        line(
+            marks,
+            corner(0, size),
+            corner(size, 0),
+            thin,
+            false,
+            Ink::Rule,
+        );
+    }
+
+    // Killer cages as dashed outlines just inside the cells, with the sum in the first cell.
+    let inset = cell * 0.08;
+    for cage in board.variant.cages.iter() {
+        for &index in cage.cells.iter() {
+            let (row, col) = (geometry.row_of(index), geometry.col_of(index));
+            let (left, top) = corner(row, col);
+            let (near_x, far_x) = (left + inset, left + cell - inset);
+            let (near_y, far_y) = (top + inset, top + cell - inset);
+            let outside = |other: usize| !cage.cells.contains(&other);
+            if row == 0 || outside(index - size) {
+                line(
+                    marks,
+                    (near_x, near_y),
+                    (far_x, near_y),
+                    thin,
+                    true,
+                    Ink::Rule,
+                );
+            }
+            if col + 1 == size || outside(index + 1) {
+                line(
+                    marks,
+                    (far_x, near_y),
+                    (far_x, far_y),
+                    thin,
+                    true,
+                    Ink::Rule,
+                );
+            }
+            if row + 1 == size || outside(index + size) {
+                line(
+                    marks,
+                    (near_x, far_y),
+                    (far_x, far_y),
+                    thin,
+                    true,
+                    Ink::Rule,
+                );
+            }
+            if col == 0 || outside(index - 1) {
+                line(
+                    marks,
+                    (near_x, near_y),
+                    (near_x, far_y),
+                    thin,
+                    true,
+                    Ink::Rule,
+                );
+            }
+        }
+        if let Some(&first) = cage.cells.iter().min() {
+            let (left, top) = corner(geometry.row_of(first), geometry.col_of(first));
+            marks.push(Mark::Text {
+                at: (left + inset * 1.5, top + inset * 2.5),
+                size: cell * 0.2,
+                text: cage.sum.to_string(),
+                ink: Ink::Caption,
+            });
+        }
+    }
+
+    // Digits, or the notes in a box-shaped grid when the cell is empty.
+    for index in 0..geometry.cells() {
+        let (left, top) = corner(geometry.row_of(index), geometry.col_of(index));
+        let value = board.values[index];
+        if value != 0 {
+            marks.push(Mark::Text {
+                at: (left + cell / 2., top + cell / 2.),
+                size: cell * 0.6,
+                text: geometry.symbol(value).to_string(),
+                ink: if given[index] { Ink::Given } else { Ink::Entry },
+            });
+            continue;
+        }
+        let notes = match notes {
+            Some(notes) => notes[index],
+            None => continue,
+        };
+        let note_width = cell / geometry.box_cols as f64;
+        let note_height = cell / geometry.box_rows as f64;
+        for note in geometry
+            .digits()
+            .filter(|note| notes & (1 << (note - 1)) != 0)
+        {
+            let position = (note - 1) as usize;
+            marks.push(Mark::Text {
+                at: (
+                    left + ((position % geometry.box_cols) as f64 + 0.5) * note_width,
+                    top + ((position / geometry.box_cols) as f64 + 0.5) * note_height,
+                ),
+                size: note_width.min(note_height) * 0.6,
+                text: geometry.symbol(note).to_string(),
+                ink: Ink::Note,
+            });
+        }
+    }
+}
+
+fn is_caption(ink: Ink) -> bool {
+    ink == Ink::Caption
+}
+
+pub fn to_svg(page: &Page) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}pt\" height=\"{h}pt\" viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"white\"/>\n",
+        w = page.width,
+        h = page.height
+    );
+    for mark in page.marks.iter() {
+        match mark {
+            Mark::Line {
+                from,
+                to,
+                width,
+                dashed,
+                ink,
+            } => {
+                let (r, g, b) = ink.rgb();
+                svg.push_str(&format!(
+                    "<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"rgb({},{},{})\" stroke-width=\"{:.2}\" stroke-linecap=\"square\"{}/>\n",
+                    from.0, from.1, to.0, to.1, r, g, b, width,
+                    if *dashed { " stroke-dasharray=\"3 3\"" } else { "" }
+                ));
+            }
+            Mark::Text {
+                at,
+                size,
+                text,
+                ink,
+            } => {
+                let (r, g, b) = ink.rgb();
+                svg.push_str(&format!(
+                    "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Helvetica, Arial, sans-serif\" font-size=\"{:.2}\" fill=\"rgb({},{},{})\" dominant-baseline=\"central\"{}{}>{}</text>\n",
+                    at.0, at.1, size, r, g, b,
+                    if ink.is_bold() { " font-weight=\"bold\"" } else { "" },
+                    if is_caption(*ink) { "" } else { " text-anchor=\"middle\"" },
+                    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+                ));
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// A plain PDF using the standard Helvetica fonts, so nothing needs embedding.
+pub fn to_pdf(pages: &[Page]) -> Vec<u8> {
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        Vec::new(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    let mut kids = Vec::new();
+    for page in pages {
+        let content = pdf_content(page);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(&content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                page.width,
+                page.height,
+                objects.len()
+            )
+            .into_bytes(),
+        );
+        kids.push(format!("{} 0 R", objects.len()));
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    )
+    .into_bytes();
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (number, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", number + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+// PDF puts the origin at the bottom left, so y is flipped.
+fn pdf_content(page: &Page) -> Vec<u8> {
+    let mut content = Vec::new();
+    for mark in page.marks.iter() {
+        match mark {
+            Mark::Line {
+                from,
+                to,
+                width,
+                dashed,
+                ink,
+            } => {
+                let (r, g, b) = ink.rgb();
+                content.extend_from_slice(
+                    format!(
+                        "{:.3} {:.3} {:.3} RG {:.2} w 2 J {} d {:.2} {:.2} m {:.2} {:.2} l S\n",
+                        f64::from(r) / 255.,
+                        f64::from(g) / 255.,
+                        f64::from(b) / 255.,
+                        width,
+                        if *dashed { "[3 3] 0" } else { "[] 0" },
+                        from.0,
+                        page.height - from.1,
+                        to.0,
+                        page.height - to.1
+                    )
+                    .as_bytes(),
+                );
+            }
+            Mark::Text {
+                at,
+                size,
+                text,
+                ink,
+            } => {
+                let (r, g, b) = ink.rgb();
+                let width: f64 = text
+                    .chars()
+                    .map(|c| helvetica_width(c, ink.is_bold()))
+                    .sum::<f64>()
+                    * size;
+                let x = if is_caption(*ink) {
+                    at.0
+                } else {
+                    at.0 - width / 2.
+                };
+                // Roughly half the height of a capital, to centre on `at`.
+                let y = page.height - at.1 - size * 0.36;
+                content.extend_from_slice(
+                    format!(
+                        "BT /{} {:.2} Tf {:.3} {:.3} {:.3} rg {:.2} {:.2} Td (",
+                        if ink.is_bold() { "F2" } else { "F1" },
+                        size,
+                        f64::from(r) / 255.,
+                        f64::from(g) / 255.,
+                        f64::from(b) / 255.,
+                        x,
+                        y
+                    )
+                    .as_bytes(),
+                );
+                content.extend(pdf_string(text));
+                content.extend_from_slice(b") Tj ET\n");
+            }
+        }
+    }
+    content
+}
+
+// Escapes the text for a PDF string in WinAnsi encoding, which covers Latin-1.
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                bytes.push(b'\\');
+                bytes.push(c as u8);
+            }
+            c if (c as u32) < 256 => bytes.push(c as u32 as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
+
+// Advance widths, in ems, for the symbols a board can show. Only symbols are centred, so
+// anything else just gets a typical width.
+fn helvetica_width(c: char, bold: bool) -> f64 {
+    match c {
+        '0'..='9' => 0.556,
+        'A' | 'B' => {
+            if bold {
+                0.722
+            } else {
+                0.667
+            }
+        }
+        'C' | 'D' => 0.722,
+        'E' => 0.667,
+        'F' => 0.611,
+        'G' => 0.778,
+        _ => 0.556,
+    }
+}
@@ -0,0 +1,430 @@
+use std::fmt;
+
+use image::DynamicImage;
+
+use crate::sudoku::geometry::{Board, Geometry};
+use crate::sudoku::rules::Variant;
+
+// Digits are compared as a coarse grid of ink coverage, scaled to fit the digit's outline.
+const FEATURE_COLS: usize = 6;
+const FEATURE_ROWS: usize = 9;
+
+// Printed sans-serif digits, 1 to 9.
+const TEMPLATES: [[&str; 12]; 9] = [
+    [
+        "...##...", ".####...", "##.##...", "...##...", "...##...", "...##...", "...##...",
+        "...##...", "...##...", "...##...", "...##...", ".######.",
+    ],
+    [
+        ".#####..", "##...##.", ".....##.", ".....##.", "....##..", "...##...", "..##....",
+        ".##.....", "##......", "##......", "##......", "#######.",
+    ],
+    [
+        ".#####..", "##...##.", ".....##.", ".....##.", "....##..", "..###...", "....##..",
+        ".....##.", ".....##.", ".....##.", "##...##.", ".#####..",
+    ],
+    [
+        "....##..", "...###..", "...###..", "..#.##..", ".#..##..", "#...##..", "#...##..",
+        "########", "....##..", "....##..", "....##..", "....##..",
+    ],
+    [
+        "#######.", "##......", "##......", "##......", "######..", ".....##.", "......##",
+        "......##", "......##", "......##", "##...##.", ".#####..",
+    ],
+    [
+        "..####..", ".##...#.", "##......", "##......", "##.###..", "###..##.", "##....##",
+        "##....##", "##....##", "##....##", ".##..##.", "..####..",
+    ],
+    [
+        "########", "......##", ".....##.", ".....##.", "....##..", "....##..", "...##...",
+        "...##...", "..##....", "..##....", ".##.....", ".##.....",
+    ],
+    [
+        "..####..", ".##..##.", "##....##", "##....##", ".##..##.", "..####..", ".##..##.",
+        "##....##", "##....##", "##....##", ".##..##.", "..####..",
+    ],
+    [
+        "..####..", ".##..##.", "##....##", "##....##", "##....##", "##....##", ".##..###",
+        "..###.##", "......##", "......##", ".#...##.", "..####..",
+    ],
+];
+
+pub enum RecognizeError {
+    NoGrid,
+    UnsupportedSize(usize),
+}
+
+impl fmt::Display for RecognizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecognizeError::NoGrid => write!(f, "Couldn't find a sudoku grid in the image"),
+            RecognizeError::UnsupportedSize(size) => write!(
+                f,
+                "The grid looks {}x{}; only boards up to 9x9 can be read from images",
+                size, size
+            ),
+        }
+    }
+}
+
+// Black and white version of the image. Faint grid lines in small or blurry pictures come out
+// grey, so the cells are found from `paper`, which only takes in the brightest pixels.
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+    paper: Vec<bool>,
+}
+
+impl Bitmap {
+    fn from_image(image: &DynamicImage) -> Bitmap {
+        let gray = image.to_luma8();
+        let threshold = otsu_threshold(gray.pixels().map(|pixel| pixel[0]));
+        let light: Vec<f64> = gray
+            .pixels()
+            .map(|pixel| pixel[0])
+            .filter(|&level| level > threshold)
+            .map(f64::from)
+            .collect();
+        let paper_level = light.iter().sum::<f64>() / light.len().max(1) as f64;
+        let paper_threshold = f64::from(threshold) + (paper_level - f64::from(threshold)) * 0.75;
+        Bitmap {
+            width: gray.width() as usize,
+            height: gray.height() as usize,
+            dark: gray.pixels().map(|pixel| pixel[0] <= threshold).collect(),
+            paper: gray
+                .pixels()
+                .map(|pixel| f64::from(pixel[0]) >= paper_threshold)
+                .collect(),
+        }
+    }
+
+    fn from_template(rows: &[&str]) -> Bitmap {
+        Bitmap {
+            width: rows[0].len(),
+            height: rows.len(),
+            dark: rows
+                .iter()
+                .flat_map(|row| row.bytes())
+                .map(|b| b == b'#')
+                .collect(),
+            paper: Vec::new(),
+        }
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Region {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Region {
+    fn width(&self) -> usize {
+        self.right - self.left + 1
+    }
+
+    fn height(&self) -> usize {
+        self.bottom - self.top + 1
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (
+            (self.left + self.right) as f64 / 2.,
+            (self.top + self.bottom) as f64 / 2.,
+        )
+    }
+}
+
+// Where the cells are: the board size, the cell centres in reading order and the cell size.
+struct Grid {
+    size: usize,
+    centers: Vec<(f64, f64)>,
+    cell: f64,
+}
+
+// Finds the grid, then reads each cell. Anything too faint or small to be a printed digit,
+// such as pencil marks, is left blank. The result should be checked by the player.
+pub fn recognize(image: &DynamicImage) -> Result<Board, RecognizeError> {
+    let bitmap = Bitmap::from_image(image);
+    let Grid {
+        size,
+        centers,
+        cell,
+    } = find_grid(&bitmap)?;
+    let geometry = match Geometry::for_size(size) {
+        Some(geometry) if size <= 9 => geometry,
+        Some(_) => return Err(RecognizeError::UnsupportedSize(size)),
+        None => return Err(RecognizeError::NoGrid),
+    };
+    let templates: Vec<Vec<f64>> = TEMPLATES
+        .iter()
+        .take(size)
+        .map(|rows| {
+            let template = Bitmap::from_template(rows);
+            let outline = ink_outline(&template, 0, 0, template.width, template.height).unwrap();
+            features(&template, outline)
+        })
+        .collect();
+    let values = centers
+        .iter()
+        .map(|&(x, y)| read_cell(&bitmap, x, y, cell, &templates))
+        .collect();
+    Ok(Board {
+        geometry,
+        variant: Variant::default(),
+        values,
+    })
+}
+
+// The cells show up as light, roughly square areas of the same size, laid out in as many rows
+// as columns.
+fn find_grid(bitmap: &Bitmap) -> Result<Grid, RecognizeError> {
+    let min_size = (bitmap.width.min(bitmap.height) / 40).max(8);
+    let candidates: Vec<Region> = light_areas(bitmap)
+        .into_iter()
+        .filter(|&(region, count)| {
+            let (width, height) = (region.width(), region.height());
+            width >= min_size
+                && height >= min_size
+                && width * 4 >= height * 3
+                && height * 4 >= width * 3
+                && count * 2 >= width * height
+        })
+        .map(|(region, _)| region)
+        .collect();
+    let similar = |a: &Region, b: &Region| {
+        (a.width() as isize - b.width() as isize).abs() * 5 <= a.width() as isize
+            && (a.height() as isize - b.height() as isize).abs() * 5 <= a.height() as isize
+    };
+    let reference = candidates
+        .iter()
+        .max_by_key(|a| candidates.iter().filter(|b| similar(a, b)).count())
+        .ok_or(RecognizeError::NoGrid)?;
+    let cells: Vec<Region> = candidates
+        .iter()
+        .filter(|b| similar(reference, b))
+        .copied()
+        .collect();
+    let mut widths: Vec<usize> = cells.iter().map(|cell| cell.width()).collect();
+    widths.sort_unstable();
+    let cell = widths[widths.len() / 2] as f64;
+    let columns = cluster(
+        cells.iter().map(|cell| cell.center().0).collect(),
+        cell / 2.,
+    );
+    let rows = cluster(
+        cells.iter().map(|cell| cell.center().1).collect(),
+        cell / 2.,
+    );
+    if columns.len() != rows.len() || columns.len() < 4 {
+        return Err(RecognizeError::NoGrid);
+    }
+    let centers = rows
+        .iter()
+        .flat_map(|&y| columns.iter().map(move |&x| (x, y)))
+        .collect();
+    Ok(Grid {
+        size: columns.len(),
+        centers,
+        cell,
+    })
+}
+
+// Connected areas of light pixels, with their pixel counts.
+fn light_areas(bitmap: &Bitmap) -> Vec<(Region, usize)> {
+    let mut seen: Vec<bool> = bitmap.paper.iter().map(|&paper| !paper).collect();
+    let mut areas = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..seen.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        stack.push(start);
+        let (x, y) = (start % bitmap.width, start / bitmap.width);
+        let mut region = Region {
+            left: x,
+            top: y,
+            right: x,
+            bottom: y,
+        };
+        let mut count = 0;
+        while let Some(index) = stack.pop() {
+            count += 1;
+            let (x, y) = (index % bitmap.width, index / bitmap.width);
+            region.left = region.left.min(x);
+            region.right = region.right.max(x);
+            region.top = region.top.min(y);
+            region.bottom = region.bottom.max(y);
+            let mut visit = |next: usize| {
+                if !seen[next] {
+                    seen[next] = true;
+                    stack.push(next);
+                }
+            };
+            if x > 0 {
+                visit(index - 1);
+            }
+            if x + 1 < bitmap.width {
+                visit(index + 1);
+            }
+            if y > 0 {
+                visit(index - bitmap.width);
+            }
+            if y + 1 < bitmap.height {
+                visit(index + bitmap.width);
+            }
+        }
+        areas.push((region, count));
+    }
+    areas
+}
+
+// Groups nearby values, returning the mean of each group in ascending order.
+fn cluster(mut values: Vec<f64>, tolerance: f64) -> Vec<f64> {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mut groups: Vec<Vec<f64>> = Vec::new();
+    for value in values {
+        match groups.last_mut() {
+            Some(group) if value - group[group.len() - 1] <= tolerance => group.push(value),
+            _ => groups.push(vec![value]),
+        }
+    }
+    groups
+        .iter()
+        .map(|group| group.iter().sum::<f64>() / group.len() as f64)
+        .collect()
+}
+
+// Looks only at the middle of the cell so the grid lines stay out of it.
+fn read_cell(bitmap: &Bitmap, x: f64, y: f64, cell: f64, templates: &[Vec<f64>]) -> u8 {
+    let half = cell * 0.45;
+    let left = (x - half).max(0.) as usize;
+    let top = (y - half).max(0.) as usize;
+    let right = ((x + half) as usize).min(bitmap.width - 1);
+    let bottom = ((y + half) as usize).min(bitmap.height - 1);
+    let outline = match ink_outline(bitmap, left, top, right + 1, bottom + 1) {
+        Some(outline) => outline,
+        None => return 0,
+    };
+    let ink = (outline.top..=outline.bottom)
+        .flat_map(|y| (outline.left..=outline.right).map(move |x| (x, y)))
+        .filter(|&(x, y)| bitmap.is_dark(x, y))
+        .count();
+    if (outline.height() as f64) < cell * 0.3 || (ink as f64) < cell * cell * 0.02 {
+        return 0;
+    }
+    let found = features(bitmap, outline);
+    let distance = |template: &Vec<f64>| -> f64 {
+        template
+            .iter()
+            .zip(found.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum()
+    };
+    templates
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(a.1).partial_cmp(&distance(b.1)).unwrap())
+        .map(|(index, _)| index as u8 + 1)
+        .unwrap()
+}
+
+// The bounding box of the dark pixels in the given area.
+fn ink_outline(
+    bitmap: &Bitmap,
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+) -> Option<Region> {
+    let mut outline: Option<Region> = None;
+    for y in top..bottom {
+        for x in left..right {
+            if bitmap.is_dark(x, y) {
+                outline = Some(match outline {
+                    Some(region) => Region {
+                        left: region.left.min(x),
+                        top: region.top.min(y),
+                        right: region.right.max(x),
+                        bottom: region.bottom.max(y),
+                    },
+                    None => Region {
+                        left: x,
+                        top: y,
+                        right: x,
+                        bottom: y,
+                    },
+                });
+            }
+        }
+    }
+    outline
+}
+
+// Ink coverage of each feature cell. The outline is scaled without stretching, so a narrow
+// digit like 1 stays narrow, and centred.
+fn features(bitmap: &Bitmap, outline: Region) -> Vec<f64> {
+    let scale = (FEATURE_COLS as f64 / outline.width() as f64)
+        .min(FEATURE_ROWS as f64 / outline.height() as f64);
+    let offset_x = (FEATURE_COLS as f64 - outline.width() as f64 * scale) / 2.;
+    let offset_y = (FEATURE_ROWS as f64 - outline.height() as f64 * scale) / 2.;
+    let mut dark = vec![0.; FEATURE_COLS * FEATURE_ROWS];
+    let mut total = vec![0.; FEATURE_COLS * FEATURE_ROWS];
+    for y in outline.top..=outline.bottom {
+        for x in outline.left..=outline.right {
+            let fx = offset_x + (x - outline.left) as f64 * scale + scale / 2.;
+            let fy = offset_y + (y - outline.top) as f64 * scale + scale / 2.;
+            let index = (fy as usize).min(FEATURE_ROWS - 1) * FEATURE_COLS
+                + (fx as usize).min(FEATURE_COLS - 1);
+            total[index] += 1.;
+            if bitmap.is_dark(x, y) {
+                dark[index] += 1.;
+            }
+        }
+    }
+    dark.iter()
+        .zip(total.iter())
+        .map(|(&dark, &total)| if total > 0. { dark / total } else { 0. })
+        .collect()
+}
+
+// Picks the grey level that best separates ink from paper.
+fn otsu_threshold(pixels: impl Iterator<Item = u8>) -> u8 {
+    let mut histogram = [0usize; 256];
+    for pixel in pixels {
+        histogram[pixel as usize] += 1;
+    }
+    let total: usize = histogram.iter().sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(level, &count)| level as f64 * count as f64)
+        .sum();
+    let mut best = (0., 127);
+    let mut below = 0;
+    let mut below_sum = 0.;
+    for (level, &count) in histogram.iter().enumerate() {
+        below += count;
+        below_sum += level as f64 * count as f64;
+        let above = total - below;
+        if below == 0 || above == 0 {
+            continue;
+        }
+        let below_mean = below_sum / below as f64;
+        let above_mean = (sum - below_sum) / above as f64;
+        let variance =
+            below as f64 * above as f64 * (below_mean - above_mean) * (below_mean - above_mean);
+        if variance > best.0 {
+            best = (variance, level as u8);
+        }
+    }
+    best.1
+}
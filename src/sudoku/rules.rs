@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sudoku::geometry::Geometry;
+
+// A constraint on top of the rows and columns. Rules that only forbid repeats express that
+// through units and peers, which the solver and the conflict check already understand; anything
+// else goes in `is_legal` and `prune`.
+pub trait Rule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Extra groups that must hold every symbol exactly once, with a name for hints.
+    fn units(&self, _geometry: Geometry) -> Vec<(String, Vec<usize>)> {
+        Vec::new()
+    }
+
+    // Irregular regions replacing the boxes, as a region number for every cell.
+    fn regions(&self) -> Option<Vec<usize>> {
+        None
+    }
+
+    // Further cells that may not repeat the value in `index`.
+    fn peers(&self, _geometry: Geometry, _index: usize) -> Vec<usize> {
+        Vec::new()
+    }
+
+    fn is_legal(&self, _board: &[u8], _index: usize, _value: u8) -> bool {
+        true
+    }
+
+    // Removes candidates the rule forbids, failing if a cell is left with none.
+    fn prune(&self, _candidates: &mut [u16]) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cage {
+    pub cells: Vec<usize>,
+    pub sum: u32,
+}
+
+// The serialisable description of which rules are in play.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub diagonals: bool,
+    pub anti_knight: bool,
+    pub anti_king: bool,
+    pub cages: Vec<Cage>,
+    pub regions: Option<Vec<usize>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Toggle {
+    Diagonals,
+    AntiKnight,
+    AntiKing,
+}
+
+impl Toggle {
+    pub fn name(self) -> &'static str {
+        match self {
+            Toggle::Diagonals => Diagonals.name(),
+            Toggle::AntiKnight => AntiKnight.name(),
+            Toggle::AntiKing => AntiKing.name(),
+        }
+    }
+}
+
+impl Variant {
+    pub fn rules(&self) -> Vec<Box<dyn Rule>> {
+        let mut rules: Vec<Box<dyn Rule>> = Vec::new();
+        if let Some(regions) = &self.regions {
+            rules.push(Box::new(Jigsaw {
+                regions: regions.clone(),
+            }));
+        }
+        if self.diagonals {
+            rules.push(Box::new(Diagonals));
+        }
+        if !self.cages.is_empty() {
+            rules.push(Box::new(Killer {
+                cages: self.cages.clone(),
+            }));
+        }
+        if self.anti_knight {
+            rules.push(Box::new(AntiKnight));
+        }
+        if self.anti_king {
+            rules.push(Box::new(AntiKing));
+        }
+        rules
+    }
+
+    pub fn is_classic(&self) -> bool {
+        *self == Variant::default()
+    }
+
+    pub fn is_set(&self, toggle: Toggle) -> bool {
+        match toggle {
+            Toggle::Diagonals => self.diagonals,
+            Toggle::AntiKnight => self.anti_knight,
+            Toggle::AntiKing => self.anti_king,
+        }
+    }
+
+    pub fn toggle(&mut self, toggle: Toggle) {
+        let flag = match toggle {
+            Toggle::Diagonals => &mut self.diagonals,
+            Toggle::AntiKnight => &mut self.anti_knight,
+            Toggle::AntiKing => &mut self.anti_king,
+        };
+        *flag = !*flag;
+    }
+
+    pub fn cage_of(&self, index: usize) -> Option<&Cage> {
+        self.cages.iter().find(|cage| cage.cells.contains(&index))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.rules().iter().map(|rule| rule.name()).collect()
+    }
+
+    // Checks that the regions and cages fit the board: every region the size of a row and
+    // every cell in at most one cage.
+    pub fn fits(&self, geometry: Geometry) -> bool {
+        let size = geometry.size();
+        let cells = geometry.cells();
+        if let Some(regions) = &self.regions {
+            if regions.len() != cells
+                || (0..size).any(|region| regions.iter().filter(|&&r| r == region).count() != size)
+            {
+                return false;
+            }
+        }
+        let mut caged = vec![false; cells];
+        for cage in self.cages.iter() {
+            for &index in cage.cells.iter() {
+                if index >= cells || caged[index] {
+                    return false;
+                }
+                caged[index] = true;
+            }
+        }
+        true
+    }
+}
+
+pub struct Diagonals;
+
+impl Rule for Diagonals {
+    fn name(&self) -> &'static str {
+        "X-Sudoku"
+    }
+
+    fn units(&self, geometry: Geometry) -> Vec<(String, Vec<usize>)> {
+        let size = geometry.size();
+        vec![
+            (
+                String::from("the main diagonal"),
+                (0..size).map(|i| i * (size + 1)).collect(),
+            ),
+            (
+                String::from("the anti-diagonal"),
+                (0..size).map(|i| (i + 1) * (size - 1)).collect(),
+            ),
+        ]
+    }
+}
+
+pub struct Jigsaw {
+    regions: Vec<usize>,
+}
+
+impl Rule for Jigsaw {
+    fn name(&self) -> &'static str {
+        "Jigsaw"
+    }
+
+    fn regions(&self) -> Option<Vec<usize>> {
+        Some(self.regions.clone())
+    }
+}
+
+// Cells of a cage never repeat and must add up to the cage's sum.
+pub struct Killer {
+    cages: Vec<Cage>,
+}
+
+impl Rule for Killer {
+    fn name(&self) -> &'static str {
+        "Killer"
+    }
+
+    fn peers(&self, _geometry: Geometry, index: usize) -> Vec<usize> {
+        match self.cages.iter().find(|cage| cage.cells.contains(&index)) {
+            Some(cage) => cage.cells.clone(),
+            Option::None => Vec::new(),
+        }
+    }
+
+    fn is_legal(&self, board: &[u8], index: usize, value: u8) -> bool {
+        let cage = match self.cages.iter().find(|cage| cage.cells.contains(&index)) {
+            Some(cage) => cage,
+            Option::None => return true,
+        };
+        let mut total = 0;
+        let mut complete = true;
+        for &cell in cage.cells.iter() {
+            let cell_value = if cell == index { value } else { board[cell] };
+            total += cell_value as u32;
+            complete &= cell_value != 0;
+        }
+        total <= cage.sum && (!complete || total == cage.sum)
+    }
+
+    // Keeps only the candidates that appear in some set of distinct digits adding up to the sum.
+    fn prune(&self, candidates: &mut [u16]) -> bool {
+        for cage in self.cages.iter() {
+            let masks: Vec<u16> = cage.cells.iter().map(|&index| candidates[index]).collect();
+            let union = masks.iter().fold(0, |union, &mask| union | mask);
+            let mut allowed = vec![0u16; masks.len()];
+            let mut digits = union;
+            loop {
+                if digits.count_ones() as usize == masks.len()
+                    && digit_sum(digits) == cage.sum
+                    && masks.iter().all(|&mask| mask & digits != 0)
+                    && masks.iter().fold(0, |cover, &mask| cover | mask & digits) == digits
+                {
+                    for (allowed, &mask) in allowed.iter_mut().zip(masks.iter()) {
+                        *allowed |= mask & digits;
+                    }
+                }
+                if digits == 0 {
+                    break;
+                }
+                digits = (digits - 1) & union;
+            }
+            for (&index, &allowed) in cage.cells.iter().zip(allowed.iter()) {
+                if allowed == 0 {
+                    return false;
+                }
+                candidates[index] &= allowed;
+            }
+        }
+        true
+    }
+}
+
+pub struct AntiKnight;
+
+impl Rule for AntiKnight {
+    fn name(&self) -> &'static str {
+        "Anti-knight"
+    }
+
+    fn peers(&self, geometry: Geometry, index: usize) -> Vec<usize> {
+        offsets(
+            geometry,
+            index,
+            &[
+                (-2, -1),
+                (-2, 1),
+                (-1, -2),
+                (-1, 2),
+                (1, -2),
+                (1, 2),
+                (2, -1),
+                (2, 1),
+            ],
+        )
+    }
+}
+
+pub struct AntiKing;
+
+impl Rule for AntiKing {
+    fn name(&self) -> &'static str {
+        "Anti-king"
+    }
+
+    fn peers(&self, geometry: Geometry, index: usize) -> Vec<usize> {
+        offsets(geometry, index, &[(-1, -1), (-1, 1), (1, -1), (1, 1)])
+    }
+}
+
+fn offsets(geometry: Geometry, index: usize, moves: &[(isize, isize)]) -> Vec<usize> {
+    let size = geometry.size() as isize;
+    let row = geometry.row_of(index) as isize;
+    let col = geometry.col_of(index) as isize;
+    moves
+        .iter()
+        .map(|&(down, across)| (row + down, col + across))
+        .filter(|&(row, col)| row >= 0 && row < size && col >= 0 && col < size)
+        .map(|(row, col)| (row * size + col) as usize)
+        .collect()
+}
+
+fn digit_sum(digits: u16) -> u32 {
+    (0..16)
+        .filter(|bit| digits & (1 << bit) != 0)
+        .map(|bit| bit + 1)
+        .sum()
+}
@@ -0,0 +1,242 @@
+use crate::sudoku::geometry::Layout;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolveResult {
+    Unique,
+    Multiple,
+    None,
+}
+
+// Lets a caller watch a search as it runs and stop it early. Every method has a default, so an
+// observer only implements what it needs.
+pub trait Observer {
+    // Checked at every node; once it returns true the search gives up.
+    fn cancelled(&self) -> bool {
+        false
+    }
+
+    // Called at every node with the number explored so far and how many guesses deep it is.
+    fn progress(&mut self, _nodes: u64, _depth: usize) {}
+
+    // Called with the board just after the search guesses a value for `index`.
+    fn guessed(&mut self, _board: &[u8], _index: usize) {}
+
+    // Called when that guess has been explored and the search returns to the board before it.
+    fn backtracked(&mut self, _board: &[u8], _index: usize) {}
+
+    // Puts the candidates for a guess in the order they're tried, lowest first by default.
+    fn order(&mut self, _digits: &mut [u8]) {}
+}
+
+struct Quiet;
+
+impl Observer for Quiet {}
+
+struct Search<'o> {
+    limit: usize,
+    solutions: Vec<Vec<u8>>,
+    nodes: u64,
+    cancelled: bool,
+    observer: &'o mut dyn Observer,
+}
+
+impl<'o> Search<'o> {
+    fn done(&self) -> bool {
+        self.cancelled || self.solutions.len() >= self.limit
+    }
+}
+
+#[derive(Clone)]
+pub struct SolverState<'a> {
+    pub layout: &'a Layout,
+    pub board: Vec<u8>,
+    pub candidates: Vec<u16>,
+}
+
+impl<'a> SolverState<'a> {
+    pub fn new(layout: &'a Layout, board: &[u8]) -> Option<SolverState<'a>> {
+        let geometry = layout.geometry;
+        let mut state = SolverState {
+            layout,
+            board: vec![0; geometry.cells()],
+            candidates: vec![geometry.all_candidates(); geometry.cells()],
+        };
+        for (index, &value) in board.iter().enumerate() {
+            if value != 0 && !state.assign(index, value) {
+                return None;
+            }
+        }
+        Some(state)
+    }
+
+    // Places the value and strips it from every peer, failing if a peer is left with no candidates.
+    pub fn assign(&mut self, index: usize, value: u8) -> bool {
+        let bit = 1 << (value - 1);
+        if self.candidates[index] & bit == 0 {
+            return false;
+        }
+        self.board[index] = value;
+        self.candidates[index] = bit;
+        for &peer in self.layout.peers[index].iter() {
+            if self.candidates[peer] & bit != 0 {
+                self.candidates[peer] &= !bit;
+                if self.candidates[peer] == 0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // Repeatedly fills naked and hidden singles, and lets the rules prune, until nothing changes.
+    fn propagate(&mut self) -> bool {
+        let layout = self.layout;
+        loop {
+            let mut progress = false;
+            for index in 0..self.board.len() {
+                if self.board[index] == 0 {
+                    let candidates = self.candidates[index];
+                    if candidates == 0 {
+                        return false;
+                    }
+                    if candidates.count_ones() == 1 {
+                        if !self.assign(index, candidates.trailing_zeros() as u8 + 1) {
+                            return false;
+                        }
+                        progress = true;
+                    }
+                }
+            }
+            for unit in layout.units.iter() {
+                for value in layout.geometry.digits() {
+                    let bit = 1 << (value - 1);
+                    let mut placed = false;
+                    let mut count = 0;
+                    let mut last = 0;
+                    for &index in unit {
+                        if self.board[index] == value {
+                            placed = true;
+                            break;
+                        }
+                        if self.candidates[index] & bit != 0 {
+                            count += 1;
+                            last = index;
+                        }
+                    }
+                    if !placed {
+                        match count {
+                            0 => return false,
+                            1 => {
+                                if !self.assign(last, value) {
+                                    return false;
+                                }
+                                progress = true;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            for rule in layout.rules.iter() {
+                let before = self.candidates.clone();
+                if !rule.prune(&mut self.candidates) {
+                    return false;
+                }
+                progress |= self.candidates != before;
+            }
+            if !progress {
+                return true;
+            }
+        }
+    }
+
+    // Guesses on the empty cell with the fewest candidates, collecting up to `limit` solutions.
+    fn search(mut self, depth: usize, search: &mut Search) {
+        search.nodes += 1;
+        search.observer.progress(search.nodes, depth);
+        if search.observer.cancelled() {
+            search.cancelled = true;
+            return;
+        }
+        if !self.propagate() {
+            return;
+        }
+        let next = (0..self.board.len())
+            .filter(|&index| self.board[index] == 0)
+            .min_by_key(|&index| self.candidates[index].count_ones());
+        match next {
+            Some(index) => {
+                let mut digits: Vec<u8> = self.layout.geometry.digits().collect();
+                search.observer.order(&mut digits);
+                for value in digits {
+                    if self.candidates[index] & (1 << (value - 1)) != 0 {
+                        let mut guess = self.clone();
+                        if guess.assign(index, value) {
+                            search.observer.guessed(&guess.board, index);
+                            guess.search(depth + 1, search);
+                            if search.done() {
+                                return;
+                            }
+                            search.observer.backtracked(&self.board, index);
+                        }
+                    }
+                }
+            }
+            None => search.solutions.push(self.board),
+        }
+    }
+}
+
+pub fn find_solutions(layout: &Layout, board: &[u8], limit: usize) -> Vec<Vec<u8>> {
+    observed_solutions(layout, board, limit, &mut Quiet).unwrap_or_default()
+}
+
+// Returns None if the observer cancelled the search.
+pub fn observed_solutions(
+    layout: &Layout,
+    board: &[u8],
+    limit: usize,
+    observer: &mut dyn Observer,
+) -> Option<Vec<Vec<u8>>> {
+    let mut search = Search {
+        limit,
+        solutions: Vec::new(),
+        nodes: 0,
+        cancelled: false,
+        observer,
+    };
+    if let Some(state) = SolverState::new(layout, board) {
+        state.search(0, &mut search);
+    }
+    if search.cancelled {
+        None
+    } else {
+        Some(search.solutions)
+    }
+}
+
+// Stops looking once a second solution turns up, which is all that uniqueness needs.
+pub fn solve(layout: &Layout, board: &mut Vec<u8>) -> SolveResult {
+    solve_observed(layout, board, &mut Quiet).unwrap()
+}
+
+// Like `solve`, but reports to the observer, and returns None without touching the board if
+// it was cancelled.
+pub fn solve_observed(
+    layout: &Layout,
+    board: &mut Vec<u8>,
+    observer: &mut dyn Observer,
+) -> Option<SolveResult> {
+    let mut solutions = observed_solutions(layout, board, 2, observer)?;
+    Some(match solutions.len() {
+        0 => SolveResult::None,
+        1 => {
+            *board = solutions.remove(0);
+            SolveResult::Unique
+        }
+        _ => {
+            *board = solutions.remove(0);
+            SolveResult::Multiple
+        }
+    })
+}
@@ -0,0 +1,608 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sudoku::geometry::{Geometry, Layout};
+use crate::sudoku::solver::SolverState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Expert => "Expert",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    CageSum,
+    PointingPair,
+    BoxLineReduction,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    XWing,
+    Swordfish,
+}
+
+impl Technique {
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::NakedSingle => "Naked single",
+            Technique::HiddenSingle => "Hidden single",
+            Technique::CageSum => "Cage sum",
+            Technique::PointingPair => "Pointing pair",
+            Technique::BoxLineReduction => "Box/line reduction",
+            Technique::NakedPair => "Naked pair",
+            Technique::HiddenPair => "Hidden pair",
+            Technique::NakedTriple => "Naked triple",
+            Technique::HiddenTriple => "Hidden triple",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "Swordfish",
+        }
+    }
+
+    pub fn difficulty(self) -> Difficulty {
+        match self {
+            Technique::NakedSingle | Technique::HiddenSingle => Difficulty::Easy,
+            Technique::CageSum | Technique::PointingPair | Technique::BoxLineReduction => {
+                Difficulty::Medium
+            }
+            Technique::NakedPair
+            | Technique::HiddenPair
+            | Technique::NakedTriple
+            | Technique::HiddenTriple => Difficulty::Hard,
+            Technique::XWing | Technique::Swordfish => Difficulty::Expert,
+        }
+    }
+}
+
+pub enum Deduction {
+    Place(usize, u8),
+    Eliminate(Vec<(usize, u8)>),
+}
+
+pub struct Step {
+    pub technique: Technique,
+    pub deduction: Deduction,
+    // The cells, digits and units (indices into Layout::units) that make up the pattern.
+    pub cells: Vec<usize>,
+    pub digits: Vec<u8>,
+    pub units: Vec<usize>,
+}
+
+impl Step {
+    fn place(technique: Technique, index: usize, value: u8, units: Vec<usize>) -> Option<Step> {
+        Some(Step {
+            technique,
+            deduction: Deduction::Place(index, value),
+            cells: vec![index],
+            digits: vec![value],
+            units,
+        })
+    }
+
+    fn eliminate(
+        technique: Technique,
+        eliminations: Vec<(usize, u8)>,
+        cells: Vec<usize>,
+        digits: Vec<u8>,
+        units: Vec<usize>,
+    ) -> Option<Step> {
+        if eliminations.is_empty() {
+            return None;
+        }
+        Some(Step {
+            technique,
+            deduction: Deduction::Eliminate(eliminations),
+            cells,
+            digits,
+            units,
+        })
+    }
+
+    // The cells the deduction changes: the placed cell, or every cell losing a candidate.
+    pub fn targets(&self) -> Vec<usize> {
+        match &self.deduction {
+            Deduction::Place(index, _) => vec![*index],
+            Deduction::Eliminate(eliminations) => {
+                let mut targets: Vec<usize> = Vec::new();
+                for &(index, _) in eliminations {
+                    if !targets.contains(&index) {
+                        targets.push(index);
+                    }
+                }
+                targets
+            }
+        }
+    }
+
+    pub fn explain(&self, layout: &Layout) -> String {
+        let geometry = layout.geometry;
+        let digits = list(
+            self.digits
+                .iter()
+                .map(|&digit| geometry.symbol(digit).to_string()),
+        );
+        let cells = list(self.cells.iter().map(|&index| cell_name(geometry, index)));
+        let targets = list(
+            self.targets()
+                .into_iter()
+                .map(|index| cell_name(geometry, index)),
+        );
+        let detail = match self.technique {
+            Technique::NakedSingle => format!("{} can only hold {}", cells, digits),
+            Technique::HiddenSingle => format!(
+                "only cell {} in {} can hold {}",
+                cells, layout.unit_names[self.units[0]], digits
+            ),
+            Technique::CageSum => format!(
+                "no combination of digits adding up to the cage totals puts {} in {}",
+                digits, targets
+            ),
+            Technique::PointingPair | Technique::BoxLineReduction => format!(
+                "{} in {} is confined to {}, so remove it from {}",
+                digits, layout.unit_names[self.units[0]], layout.unit_names[self.units[1]], targets
+            ),
+            Technique::NakedPair | Technique::NakedTriple => format!(
+                "{} in {} hold only {}, so remove those from {}",
+                cells, layout.unit_names[self.units[0]], digits, targets
+            ),
+            Technique::HiddenPair | Technique::HiddenTriple => format!(
+                "{} fit only in {} of {}, so remove the other candidates there",
+                digits, cells, layout.unit_names[self.units[0]]
+            ),
+            Technique::XWing | Technique::Swordfish => {
+                let (base, cover) = self.units.split_at(self.units.len() / 2);
+                format!(
+                    "{} in {} is confined to {}, so remove it from {}",
+                    digits,
+                    lines_name(geometry, base),
+                    lines_name(geometry, cover),
+                    targets
+                )
+            }
+        };
+        format!("{}: {}", self.technique.name(), detail)
+    }
+
+    pub fn apply(&self, state: &mut SolverState) -> bool {
+        match &self.deduction {
+            Deduction::Place(index, value) => state.assign(*index, *value),
+            Deduction::Eliminate(eliminations) => {
+                for &(index, value) in eliminations {
+                    state.candidates[index] &= !bit(value);
+                    if state.candidates[index] == 0 {
+                        return false;
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+// Techniques are tried from easiest to hardest so the first step found is the simplest one available.
+pub fn find_step(state: &SolverState) -> Option<Step> {
+    naked_single(state)
+        .or_else(|| hidden_single(state))
+        .or_else(|| cage_sum(state))
+        .or_else(|| pointing_pair(state))
+        .or_else(|| box_line_reduction(state))
+        .or_else(|| naked_subset(state, 2))
+        .or_else(|| hidden_subset(state, 2))
+        .or_else(|| naked_subset(state, 3))
+        .or_else(|| hidden_subset(state, 3))
+        .or_else(|| fish(state, 2))
+        .or_else(|| fish(state, 3))
+}
+
+// Looks for the next deduction on a board, ignoring candidates earlier hints already removed.
+pub fn find_hint(layout: &Layout, board: &[u8], eliminated: &[u16]) -> Option<Step> {
+    let mut state = SolverState::new(layout, board)?;
+    for (index, &mask) in eliminated.iter().enumerate() {
+        if state.board[index] == 0 {
+            state.candidates[index] &= !mask;
+        }
+    }
+    find_step(&state)
+}
+
+// Grades by the hardest technique needed; puzzles the techniques can't finish need guessing and count as Expert.
+pub fn grade(layout: &Layout, board: &[u8]) -> Difficulty {
+    let mut state = match SolverState::new(layout, board) {
+        Some(state) => state,
+        None => return Difficulty::Expert,
+    };
+    let mut hardest = Difficulty::Easy;
+    while state.board.contains(&0) {
+        match find_step(&state) {
+            Some(step) => {
+                hardest = hardest.max(step.technique.difficulty());
+                if !step.apply(&mut state) {
+                    return Difficulty::Expert;
+                }
+            }
+            None => return Difficulty::Expert,
+        }
+    }
+    hardest
+}
+
+pub fn cell_name(geometry: Geometry, index: usize) -> String {
+    format!(
+        "r{}c{}",
+        geometry.row_of(index) + 1,
+        geometry.col_of(index) + 1
+    )
+}
+
+// Names several rows or several columns together, e.g. "rows 2 and 6".
+fn lines_name(geometry: Geometry, units: &[usize]) -> String {
+    let size = geometry.size();
+    let kind = if units[0] < size { "rows" } else { "columns" };
+    format!(
+        "{} {}",
+        kind,
+        list(units.iter().map(|unit| (unit % size + 1).to_string()))
+    )
+}
+
+pub fn list(items: impl Iterator<Item = String>) -> String {
+    let items: Vec<String> = items.collect();
+    match items.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        Some((last, _)) => last.clone(),
+        None => String::new(),
+    }
+}
+
+fn bit(value: u8) -> u16 {
+    1 << (value - 1)
+}
+
+fn is_open(state: &SolverState, index: usize, value: u8) -> bool {
+    state.board[index] == 0 && state.candidates[index] & bit(value) != 0
+}
+
+fn is_placed(state: &SolverState, unit: &[usize], value: u8) -> bool {
+    unit.iter().any(|&index| state.board[index] == value)
+}
+
+fn positions(state: &SolverState, unit: &[usize], value: u8) -> Vec<usize> {
+    unit.iter()
+        .copied()
+        .filter(|&index| is_open(state, index, value))
+        .collect()
+}
+
+// Every way of choosing `k` of `n` items, as lists of item positions.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for last in k - 1..n {
+        for mut combination in combinations(last, k - 1) {
+            combination.push(last);
+            result.push(combination);
+        }
+    }
+    result
+}
+
+fn naked_single(state: &SolverState) -> Option<Step> {
+    (0..state.board.len())
+        .find(|&index| state.board[index] == 0 && state.candidates[index].count_ones() == 1)
+        .and_then(|index| {
+            let value = state.candidates[index].trailing_zeros() as u8 + 1;
+            Step::place(Technique::NakedSingle, index, value, Vec::new())
+        })
+}
+
+fn hidden_single(state: &SolverState) -> Option<Step> {
+    for (unit_index, unit) in state.layout.units.iter().enumerate() {
+        for value in state.layout.geometry.digits() {
+            if !is_placed(state, unit, value) {
+                let cells = positions(state, unit, value);
+                if cells.len() == 1 {
+                    return Step::place(Technique::HiddenSingle, cells[0], value, vec![unit_index]);
+                }
+            }
+        }
+    }
+    None
+}
+
+// Rules with their own pruning, which in practice means Killer cages, rule out candidates directly.
+fn cage_sum(state: &SolverState) -> Option<Step> {
+    for rule in state.layout.rules.iter() {
+        let mut candidates = state.candidates.clone();
+        if !rule.prune(&mut candidates) {
+            continue;
+        }
+        let mut eliminations = Vec::new();
+        for (index, (&before, &after)) in state.candidates.iter().zip(candidates.iter()).enumerate()
+        {
+            for value in state.layout.geometry.digits() {
+                if before & !after & bit(value) != 0 && state.board[index] == 0 {
+                    eliminations.push((index, value));
+                }
+            }
+        }
+        let mut digits: Vec<u8> = eliminations.iter().map(|&(_, value)| value).collect();
+        digits.sort_unstable();
+        digits.dedup();
+        if let Some(step) = Step::eliminate(
+            Technique::CageSum,
+            eliminations,
+            Vec::new(),
+            digits,
+            Vec::new(),
+        ) {
+            return Some(step);
+        }
+    }
+    None
+}
+
+// A digit confined to one row or column inside a box can't appear elsewhere on that line.
+fn pointing_pair(state: &SolverState) -> Option<Step> {
+    let geometry = state.layout.geometry;
+    let size = geometry.size();
+    for (grid, unit) in state.layout.boxes().iter().enumerate() {
+        for value in geometry.digits() {
+            let cells = positions(state, unit, value);
+            if cells.len() < 2 {
+                continue;
+            }
+            let row = geometry.row_of(cells[0]);
+            let col = geometry.col_of(cells[0]);
+            let line = if cells.iter().all(|&index| geometry.row_of(index) == row) {
+                row
+            } else if cells.iter().all(|&index| geometry.col_of(index) == col) {
+                size + col
+            } else {
+                continue;
+            };
+            let eliminations = state.layout.units[line]
+                .iter()
+                .filter(|&&index| {
+                    state.layout.regions[index] != grid && is_open(state, index, value)
+                })
+                .map(|&index| (index, value))
+                .collect();
+            if let Some(step) = Step::eliminate(
+                Technique::PointingPair,
+                eliminations,
+                cells,
+                vec![value],
+                vec![2 * size + grid, line],
+            ) {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+// A digit confined to one box along a row or column can't appear elsewhere in that box.
+fn box_line_reduction(state: &SolverState) -> Option<Step> {
+    let geometry = state.layout.geometry;
+    let size = geometry.size();
+    for (line_index, line) in state.layout.units[0..2 * size].iter().enumerate() {
+        for value in geometry.digits() {
+            let cells = positions(state, line, value);
+            if cells.len() < 2
+                || cells
+                    .iter()
+                    .any(|&index| state.layout.regions[index] != state.layout.regions[cells[0]])
+            {
+                continue;
+            }
+            let grid = 2 * size + state.layout.regions[cells[0]];
+            let eliminations = state.layout.units[grid]
+                .iter()
+                .filter(|&&index| !line.contains(&index) && is_open(state, index, value))
+                .map(|&index| (index, value))
+                .collect();
+            if let Some(step) = Step::eliminate(
+                Technique::BoxLineReduction,
+                eliminations,
+                cells,
+                vec![value],
+                vec![line_index, grid],
+            ) {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+// `size` cells in a unit sharing exactly `size` candidates claim those digits for themselves.
+fn naked_subset(state: &SolverState, size: usize) -> Option<Step> {
+    let technique = if size == 2 {
+        Technique::NakedPair
+    } else {
+        Technique::NakedTriple
+    };
+    let digits = state.layout.geometry.digits();
+    for (unit_index, unit) in state.layout.units.iter().enumerate() {
+        let open: Vec<usize> = unit
+            .iter()
+            .copied()
+            .filter(|&index| state.board[index] == 0)
+            .collect();
+        if open.len() <= size {
+            continue;
+        }
+        for chosen in combinations(open.len(), size) {
+            let cells: Vec<usize> = chosen.iter().map(|&i| open[i]).collect();
+            let mask = cells
+                .iter()
+                .fold(0, |mask, &index| mask | state.candidates[index]);
+            if mask.count_ones() as usize != size {
+                continue;
+            }
+            let mut eliminations = Vec::new();
+            for &index in open.iter().filter(|index| !cells.contains(index)) {
+                for value in digits.clone() {
+                    if mask & bit(value) != 0 && is_open(state, index, value) {
+                        eliminations.push((index, value));
+                    }
+                }
+            }
+            let digits = digits
+                .clone()
+                .filter(|&value| mask & bit(value) != 0)
+                .collect();
+            if let Some(step) =
+                Step::eliminate(technique, eliminations, cells, digits, vec![unit_index])
+            {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+// `size` digits that fit in only `size` cells of a unit push every other candidate out of those cells.
+fn hidden_subset(state: &SolverState, size: usize) -> Option<Step> {
+    let technique = if size == 2 {
+        Technique::HiddenPair
+    } else {
+        Technique::HiddenTriple
+    };
+    let geometry = state.layout.geometry;
+    for (unit_index, unit) in state.layout.units.iter().enumerate() {
+        let digits: Vec<u8> = geometry
+            .digits()
+            .filter(|&value| !is_placed(state, unit, value))
+            .collect();
+        if digits.len() <= size {
+            continue;
+        }
+        for chosen in combinations(digits.len(), size) {
+            let values: Vec<u8> = chosen.iter().map(|&i| digits[i]).collect();
+            let mut cells: Vec<usize> = Vec::new();
+            for &value in values.iter() {
+                for index in positions(state, unit, value) {
+                    if !cells.contains(&index) {
+                        cells.push(index);
+                    }
+                }
+            }
+            if cells.len() != size {
+                continue;
+            }
+            let mask = values.iter().fold(0, |mask, &value| mask | bit(value));
+            let mut eliminations = Vec::new();
+            for &index in cells.iter() {
+                for value in geometry.digits() {
+                    if mask & bit(value) == 0 && is_open(state, index, value) {
+                        eliminations.push((index, value));
+                    }
+                }
+            }
+            if let Some(step) =
+                Step::eliminate(technique, eliminations, cells, values, vec![unit_index])
+            {
+                return Some(step);
+            }
+        }
+    }
+    None
+}
+
+// X-Wing for two lines, Swordfish for three: a digit covered by as many crossing lines
+// as base lines can be removed from the rest of those crossing lines.
+fn fish(state: &SolverState, size: usize) -> Option<Step> {
+    let technique = if size == 2 {
+        Technique::XWing
+    } else {
+        Technique::Swordfish
+    };
+    let geometry = state.layout.geometry;
+    let n = geometry.size();
+    for value in geometry.digits() {
+        for &(base, cover) in [(0, n), (n, 0)].iter() {
+            let lines: Vec<(usize, Vec<usize>)> = (0..n)
+                .map(|line| {
+                    let cells = positions(state, &state.layout.units[base + line], value);
+                    let crossings = cells
+                        .iter()
+                        .map(|&index| {
+                            if base == 0 {
+                                geometry.col_of(index)
+                            } else {
+                                geometry.row_of(index)
+                            }
+                        })
+                        .collect();
+                    (line, crossings)
+                })
+                .filter(|(_, crossings): &(usize, Vec<usize>)| {
+                    crossings.len() >= 2 && crossings.len() <= size
+                })
+                .collect();
+            if lines.len() < size {
+                continue;
+            }
+            for chosen in combinations(lines.len(), size) {
+                let mut crossings: Vec<usize> = Vec::new();
+                for &i in chosen.iter() {
+                    for &crossing in lines[i].1.iter() {
+                        if !crossings.contains(&crossing) {
+                            crossings.push(crossing);
+                        }
+                    }
+                }
+                if crossings.len() != size {
+                    continue;
+                }
+                let base_lines: Vec<usize> = chosen.iter().map(|&i| lines[i].0).collect();
+                let cells = base_lines
+                    .iter()
+                    .flat_map(|&line| positions(state, &state.layout.units[base + line], value))
+                    .collect();
+                let units = base_lines
+                    .iter()
+                    .map(|&line| base + line)
+                    .chain(crossings.iter().map(|&crossing| cover + crossing))
+                    .collect();
+                let mut eliminations = Vec::new();
+                for &crossing in crossings.iter() {
+                    for &index in state.layout.units[cover + crossing].iter() {
+                        let line = if base == 0 {
+                            geometry.row_of(index)
+                        } else {
+                            geometry.col_of(index)
+                        };
+                        if !base_lines.contains(&line) && is_open(state, index, value) {
+                            eliminations.push((index, value));
+                        }
+                    }
+                }
+                if let Some(step) =
+                    Step::eliminate(technique, eliminations, cells, vec![value], units)
+                {
+                    return Some(step);
+                }
+            }
+        }
+    }
+    None
+}
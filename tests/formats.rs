@@ -0,0 +1,46 @@
+use druid_play::sudoku::formats;
+
+// A grid row of nothing but '-' blanks is a row, not a separator between boxes.
+#[test]
+fn all_blank_row_is_kept() {
+    for blank_row in ["---------", "- - - - - - - - -", "- - - | - - - | - - -"].iter() {
+        let text = format!(
+            "53--7----\n6--195---\n-98----6-\n------+------\n{}\n4--8-3--1\n7---2---6\n\
+             -6----28-\n---419--5\n----8--79\n",
+            blank_row
+        );
+        let puzzles = match formats::parse(&text) {
+            Ok(puzzles) => puzzles,
+            Err(e) => panic!("{}: {}", blank_row, e),
+        };
+        assert_eq!(puzzles.len(), 1);
+        assert!(puzzles[0].values[27..36].iter().all(|&value| value == 0));
+        assert_eq!(puzzles[0].values[36], 4);
+    }
+}
+
+// Dashes that couldn't be a row still rule off boxes.
+#[test]
+fn dash_separators_are_skipped() {
+    let text = "5 3 - | - 7 - | - - -\n6 - - | 1 9 5 | - - -\n- 9 8 | - - - | - 6 -\n\
+                ---------------------\n8 - - | - 6 - | - - 3\n4 - - | 8 - 3 | - - 1\n\
+                7 - - | - 2 - | - - 6\n---------------------\n- 6 - | - - - | 2 8 -\n\
+                - - - | 4 1 9 | - - 5\n- - - | - 8 - | - 7 9\n";
+    let puzzles = formats::parse(text).ok().expect("grid with dash separators");
+    assert_eq!(puzzles.len(), 1);
+    assert_eq!(puzzles[0].values[27], 8);
+}
+
+// A bad puzzle in a batch leaves the puzzles around it readable.
+#[test]
+fn parse_each_carries_on_after_a_bad_puzzle() {
+    let text = "1234341221434321\n12?4341221434321\n1 2 3 4\n3 4 1 2\n2 1 ? 3\n4 3 2 1\n\
+                0000341221434321\n";
+    let puzzles = formats::parse_each(text);
+    assert_eq!(puzzles.len(), 4);
+    assert!(puzzles[0].is_ok());
+    assert!(puzzles[1].is_err());
+    assert!(puzzles[2].is_err());
+    assert!(puzzles[3].is_ok());
+    assert!(formats::parse(text).is_err());
+}